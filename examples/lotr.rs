@@ -2,7 +2,7 @@ use std::fs;
 
 use anyhow::Result;
 use csv::ReaderBuilder;
-use fuzzy_finder::{item::Item, FuzzyFinder};
+use fuzzy_finder::{item::Item, FindOutcome, FuzzyFinderBuilder};
 use serde::Deserialize;
 
 #[derive(Debug, Deserialize, Clone)]
@@ -26,17 +26,21 @@ fn main() -> Result<()> {
         characters.push(Item::new(record.name.clone(), record));
     }
 
-    // Do the find
-    let result = FuzzyFinder::find(characters, 8)?;
+    // Do the find, showing each character's bio next to the list
+    let result = FuzzyFinderBuilder::new()
+        .preview(|character: &LotrCharacter| character.bio.trim().to_string())
+        .find(characters)?;
 
     // Handle the result
     match result {
-        Some(result) => println!(
+        FindOutcome::Selected { item, .. } => println!(
             "Ah, a fascinating character is {}. Let me tell you about them: {}",
-            result.name, result.bio
+            item.name, item.bio
         ),
-
-        None => println!("Whatever, philistine."),
+        FindOutcome::NoMatch { query, .. } => {
+            println!("I've never heard of {query}. Are you sure they're in the books?")
+        }
+        _ => println!("Whatever, philistine."),
     }
     Ok(())
 }
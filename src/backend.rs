@@ -0,0 +1,155 @@
+//! Where the finder gets keys from and draws to.
+//!
+//! The finder normally uses the terminal, but anything that implements [`Backend`] will
+//! do. [`TestBackend`] keeps everything in memory, for testing.
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io;
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::keymap::Key;
+use crate::state::Frame;
+use crate::theme::Theme;
+use crate::Result;
+
+/// Something that happened that the finder needs to respond to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    Key(Key),
+    /// Text pasted into the terminal.
+    Paste(String),
+    /// The space there is to draw in has changed, see [`Backend::size`].
+    Resize,
+}
+
+pub trait Backend {
+    /// How many columns there are, and how many rows for items, not counting the rows
+    /// for the number of matches and the prompt below them.
+    fn size(&self) -> (usize, usize);
+
+    /// Waits up to `timeout` for something to happen, and returns `None` if nothing
+    /// did. The finder takes in new items between calls, so it asks again soon while
+    /// they're arriving, and otherwise leaves the backend to block.
+    fn event(&mut self, timeout: Duration) -> Result<Option<Event>>;
+
+    /// Shows `frame`, in `theme`'s colours if the backend has colours.
+    fn draw(&mut self, frame: &Frame, theme: &Theme) -> Result<()>;
+}
+
+/// Which library opens the terminal and reads keys from it, when no [`Backend`] is
+/// given. Each needs its cargo feature turned on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Driver {
+    /// Needs the `termion` feature.
+    Termion,
+    /// Needs the `crossterm` feature, and suits applications that already use it. Only
+    /// on Unix for now, like termion.
+    Crossterm,
+}
+
+impl Default for Driver {
+    /// Termion if its feature is on, as it is by default, otherwise crossterm.
+    fn default() -> Self {
+        if cfg!(feature = "termion") {
+            Driver::Termion
+        } else {
+            Driver::Crossterm
+        }
+    }
+}
+
+/// A backend that keeps everything in memory, for testing. Queue up events and hand a
+/// clone to [`FuzzyFinderBuilder::backend`](crate::FuzzyFinderBuilder::backend), then
+/// look at what was drawn with the one you kept, as clones share everything.
+///
+/// Once the events run out, [`Backend::event`] fails with
+/// [`UnexpectedEof`](io::ErrorKind::UnexpectedEof), which ends the search.
+///
+/// ```
+/// use fuzzy_finder::backend::TestBackend;
+/// use fuzzy_finder::item::Item;
+/// use fuzzy_finder::keymap::Key;
+/// use fuzzy_finder::FuzzyFinderBuilder;
+///
+/// let backend = TestBackend::new(20, 3);
+/// backend.push_str("sam");
+/// backend.push_key(Key::Up);
+/// let items = vec![Item::new(String::from("Frodo"), 1), Item::new(String::from("Sam"), 2)];
+/// let result = FuzzyFinderBuilder::new().backend(backend.clone()).find(items);
+/// assert!(result.is_err());
+/// assert_eq!(backend.screen()[2], ">  Sam");
+/// ```
+#[derive(Clone, Default)]
+pub struct TestBackend {
+    inner: Rc<RefCell<TestBackendInner>>,
+}
+
+#[derive(Default)]
+struct TestBackendInner {
+    size: (usize, usize),
+    events: VecDeque<Event>,
+    frames: Vec<Frame>,
+}
+
+impl TestBackend {
+    /// A backend `width` columns wide, with `height` rows for items.
+    pub fn new(width: usize, height: usize) -> Self {
+        let backend = TestBackend::default();
+        backend.inner.borrow_mut().size = (width, height);
+        backend
+    }
+
+    pub fn push_event(&self, event: Event) {
+        self.inner.borrow_mut().events.push_back(event);
+    }
+
+    pub fn push_key(&self, key: Key) {
+        self.push_event(Event::Key(key));
+    }
+
+    /// Types `text` a char at a time.
+    pub fn push_str(&self, text: &str) {
+        for c in text.chars() {
+            self.push_key(Key::Char(c));
+        }
+    }
+
+    /// Changes the size, and lets the finder know.
+    pub fn resize(&self, width: usize, height: usize) {
+        self.inner.borrow_mut().size = (width, height);
+        self.push_event(Event::Resize);
+    }
+
+    /// Every frame that's been drawn, oldest first.
+    pub fn frames(&self) -> Vec<Frame> {
+        self.inner.borrow().frames.clone()
+    }
+
+    /// The last frame drawn as plain text, see [`Frame::lines`].
+    pub fn screen(&self) -> Vec<String> {
+        self.inner
+            .borrow()
+            .frames
+            .last()
+            .map_or(vec![], Frame::lines)
+    }
+}
+
+impl Backend for TestBackend {
+    fn size(&self) -> (usize, usize) {
+        self.inner.borrow().size
+    }
+
+    fn event(&mut self, _timeout: Duration) -> Result<Option<Event>> {
+        match self.inner.borrow_mut().events.pop_front() {
+            Some(event) => Ok(Some(event)),
+            None => Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+        }
+    }
+
+    fn draw(&mut self, frame: &Frame, _theme: &Theme) -> Result<()> {
+        self.inner.borrow_mut().frames.push(frame.clone());
+        Ok(())
+    }
+}
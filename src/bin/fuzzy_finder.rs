@@ -0,0 +1,237 @@
+//! Reads lines from stdin, lets the user pick from them, and prints the choice to
+//! stdout. The finder itself is drawn on the terminal, so this can sit in the middle
+//! of a pipeline, e.g. `git branch | fuzzy_finder | xargs git checkout`.
+//!
+//! Exit codes:
+//! - 0: something was chosen
+//! - 1: nothing matched
+//! - 2: something went wrong, e.g. a bad argument
+//! - 130: the user gave up, e.g. with Esc or Ctrl-C
+use std::error::Error;
+use std::io::{self, stdin, BufRead, BufReader, Write};
+use std::process::ExitCode;
+
+use fuzzy_finder::item::Item;
+use fuzzy_finder::theme::Theme;
+use fuzzy_finder::{FindOutcome, FuzzyFinderBuilder};
+
+const USAGE: &str = "\
+Usage: fuzzy_finder [OPTIONS] < lines
+
+Options:
+      --height <N>       How many matches to show at once [default: 8]
+      --full-screen      Use the whole terminal, rather than drawing below the cursor
+      --prompt <TEXT>    The text shown in front of the search term [default: $]
+  -m, --multi            Allow choosing several lines with Tab
+      --preview <CMD>    Show what CMD prints for the selected line, with {} replaced by the line
+      --color <SPEC>     Colours, in the same form as fzf's --color, e.g. light,prompt:#ff8700
+  -h, --help             Print this help";
+
+const CHOSEN: u8 = 0;
+const NO_MATCH: u8 = 1;
+const ERROR: u8 = 2;
+/// What shells use for a program stopped by Ctrl-C, as fzf does.
+const ABORTED: u8 = 130;
+
+struct Args {
+    height: usize,
+    full_screen: bool,
+    prompt: Option<String>,
+    multi: bool,
+    preview: Option<String>,
+    theme: Option<Theme>,
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{USAGE}");
+            return ExitCode::from(CHOSEN);
+        }
+        Err(message) => {
+            eprintln!("fuzzy_finder: {message}\n\n{USAGE}");
+            return ExitCode::from(ERROR);
+        }
+    };
+
+    match run(args) {
+        Ok(FindOutcome::Aborted { .. }) => ExitCode::from(ABORTED),
+        Ok(FindOutcome::NoMatch { .. }) => ExitCode::from(NO_MATCH),
+        Ok(outcome) => {
+            let mut stdout = io::stdout().lock();
+            for line in outcome.into_items() {
+                if writeln!(stdout, "{line}").is_err() {
+                    return ExitCode::from(ERROR);
+                }
+            }
+            ExitCode::from(CHOSEN)
+        }
+        Err(e) => {
+            eprintln!("fuzzy_finder: {}", report(&e));
+            ExitCode::from(ERROR)
+        }
+    }
+}
+
+fn run(args: Args) -> fuzzy_finder::Result<FindOutcome<String>> {
+    let lines = lines(BufReader::new(stdin())).map(|line| Item::new(line.clone(), line));
+
+    let mut builder = FuzzyFinderBuilder::new()
+        .height(args.height)
+        .full_screen(args.full_screen);
+    if let Some(prompt) = args.prompt {
+        builder = builder.prompt(prompt);
+    }
+    if let Some(preview) = args.preview {
+        builder = builder.preview_command(preview);
+    }
+    if let Some(theme) = args.theme {
+        builder = builder.theme(theme);
+    }
+    if args.multi {
+        builder.find_multi_stream(lines)
+    } else {
+        builder.find_stream(lines)
+    }
+}
+
+/// `error` and what caused it, e.g. "couldn't open the terminal: No such device".
+fn report(error: &dyn Error) -> String {
+    let mut report = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        report.push_str(&format!(": {cause}"));
+        source = cause.source();
+    }
+    report
+}
+
+/// The lines of `input`, without their line endings. Anything that isn't valid UTF-8
+/// is replaced with U+FFFD rather than ending the input early.
+fn lines(input: impl BufRead) -> impl Iterator<Item = String> {
+    input.split(b'\n').map_while(Result::ok).map(|mut line| {
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        String::from_utf8_lossy(&line).into_owned()
+    })
+}
+
+/// Returns `None` if the user asked for help.
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut parsed = Args {
+        height: 8,
+        full_screen: false,
+        prompt: None,
+        multi: false,
+        preview: None,
+        theme: None,
+    };
+    while let Some(arg) = args.next() {
+        // Accept both `--height 10` and `--height=10`
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value)),
+            _ => (arg.clone(), None),
+        };
+        let mut value = || {
+            inline_value
+                .map(str::to_string)
+                .or_else(|| args.next())
+                .ok_or(format!("{flag} needs a value"))
+        };
+        match flag.as_str() {
+            "--height" => {
+                let height = value()?;
+                parsed.height = match height.parse() {
+                    Ok(height) if height > 0 => height,
+                    _ => return Err(format!("--height must be a positive number, not {height}")),
+                };
+            }
+            "--full-screen" => parsed.full_screen = true,
+            "--prompt" => parsed.prompt = Some(value()?),
+            "--preview" => parsed.preview = Some(value()?),
+            "--color" => {
+                let spec = value()?;
+                parsed.theme = Some(Theme::parse(&spec).map_err(|e| format!("--color: {e}"))?);
+            }
+            "-m" | "--multi" => parsed.multi = true,
+            "-h" | "--help" => return Ok(None),
+            _ => return Err(format!("unknown argument {arg}")),
+        }
+    }
+    Ok(Some(parsed))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Args>, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_lines() {
+        // WHEN
+        let lines: Vec<String> = lines(Cursor::new(b"a\r\n\xffb\nc")).collect();
+
+        // THEN
+        assert_eq!(lines, ["a", "\u{FFFD}b", "c"]);
+    }
+
+    #[test]
+    fn test_report() {
+        // GIVEN
+        let error = fuzzy_finder::Error::NotATty(io::Error::other("no tty"));
+
+        // WHEN
+        let report = report(&error);
+
+        // THEN
+        assert_eq!(report, "couldn't open the terminal: no tty");
+    }
+
+    #[test]
+    fn test_parse_args() {
+        // WHEN
+        let args = parse(&["--height=3", "-m", "--prompt", "hobbit>"])
+            .unwrap()
+            .unwrap();
+
+        // THEN
+        assert_eq!(args.height, 3);
+        assert!(args.multi);
+        assert_eq!(args.prompt.as_deref(), Some("hobbit>"));
+        assert!(!args.full_screen);
+    }
+
+    #[test]
+    fn test_parse_bad_args() {
+        // WHEN
+        let zero = parse(&["--height", "0"]).err();
+        let not_a_number = parse(&["--height=x"]).err();
+        let missing = parse(&["--height"]).err();
+        let unknown = parse(&["--bilbo"]).err();
+
+        // THEN
+        assert_eq!(zero.unwrap(), "--height must be a positive number, not 0");
+        assert_eq!(
+            not_a_number.unwrap(),
+            "--height must be a positive number, not x"
+        );
+        assert_eq!(missing.unwrap(), "--height needs a value");
+        assert_eq!(unknown.unwrap(), "unknown argument --bilbo");
+    }
+
+    #[test]
+    fn test_parse_help() {
+        // WHEN
+        let help = parse(&["--height", "3", "--help", "--bilbo"]);
+
+        // THEN
+        assert!(matches!(help, Ok(None)));
+    }
+}
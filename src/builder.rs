@@ -0,0 +1,397 @@
+use crate::backend::{Backend, Driver};
+use crate::item::Item;
+use crate::keymap::Keymap;
+use crate::matcher::{Matcher, SkimMatcher};
+use crate::preview::Preview;
+use crate::state::FinderState;
+use crate::theme::Theme;
+use crate::view::ViewStrategy;
+use crate::{FindOutcome, FuzzyFinder, Result};
+#[cfg(feature = "termion")]
+use std::io::{Read, Write};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+/// Configures a [`FuzzyFinder`] before running it.
+///
+/// ```no_run
+/// use fuzzy_finder::{item::Item, view::ViewStrategy, FuzzyFinderBuilder};
+///
+/// let items = vec![Item::new(String::from("Frodo"), 1)];
+/// let result = FuzzyFinderBuilder::new()
+///     .prompt(">")
+///     .height(12)
+///     .view(ViewStrategy::Fixed)
+///     .find(items);
+/// ```
+pub struct FuzzyFinderBuilder<T> {
+    pub(crate) prompt: String,
+    pub(crate) height: usize,
+    pub(crate) full_screen: bool,
+    pub(crate) view: ViewStrategy,
+    pub(crate) matcher: Box<dyn Matcher>,
+    pub(crate) extended: bool,
+    pub(crate) preview: Option<Preview<T>>,
+    pub(crate) keymap: Keymap,
+    pub(crate) theme: Theme,
+    #[cfg(feature = "termion")]
+    pub(crate) io: Option<(Box<dyn Read + Send>, Box<dyn Write>)>,
+    pub(crate) driver: Driver,
+    pub(crate) backend: Option<Box<dyn Backend>>,
+}
+
+impl<T> Default for FuzzyFinderBuilder<T> {
+    fn default() -> Self {
+        FuzzyFinderBuilder {
+            prompt: String::from("$"),
+            height: 8,
+            full_screen: false,
+            view: ViewStrategy::default(),
+            matcher: Box::new(SkimMatcher::default()),
+            extended: true,
+            preview: None,
+            keymap: Keymap::default(),
+            theme: Theme::default(),
+            #[cfg(feature = "termion")]
+            io: None,
+            driver: Driver::default(),
+            backend: None,
+        }
+    }
+}
+
+impl<T> FuzzyFinderBuilder<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The text shown in front of the search term.
+    pub fn prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.prompt = prompt.into();
+        self
+    }
+
+    /// How many matches to show at once, if there are that many rows below the cursor.
+    /// Otherwise as many as fit. 0 is treated as 1.
+    pub fn height(mut self, height: usize) -> Self {
+        self.height = height;
+        self
+    }
+
+    /// Takes over the whole terminal, like fzf does by default, rather than drawing
+    /// below the cursor. `height` is ignored, and whatever was on screen comes back
+    /// when the finder closes.
+    pub fn full_screen(mut self, full_screen: bool) -> Self {
+        self.full_screen = full_screen;
+        self
+    }
+
+    /// How the matches are laid out and scrolled.
+    pub fn view(mut self, view: ViewStrategy) -> Self {
+        self.view = view;
+        self
+    }
+
+    /// The algorithm used to score items against the search term. See [`crate::matcher`]
+    /// for the built-in ones.
+    pub fn matcher(mut self, matcher: impl Matcher + 'static) -> Self {
+        self.matcher = Box::new(matcher);
+        self
+    }
+
+    /// Whether to understand fzf's extended search syntax, see [`crate::query`]. On by
+    /// default. When off, the whole search term is handed to the matcher as it is, as it
+    /// always is for matchers that don't support it, like [`RegexMatcher`](crate::matcher::RegexMatcher).
+    pub fn extended(mut self, extended: bool) -> Self {
+        self.extended = extended;
+        self
+    }
+
+    /// Shows the result of calling `preview` on the selected item's data in a pane to
+    /// the right of the matches.
+    pub fn preview(mut self, preview: impl Fn(&T) -> String + 'static) -> Self {
+        self.preview = Some(Preview::Function(Box::new(preview)));
+        self
+    }
+
+    /// Shows what `command` prints in a pane to the right of the matches. `{}` in the
+    /// command is replaced with the selected item's name, e.g. `cat {}`. The command
+    /// is run with `sh -c` in the background each time the selection changes, and the
+    /// last preview stays up until it finishes.
+    pub fn preview_command(mut self, command: impl Into<String>) -> Self {
+        self.preview = Some(Preview::Command(command.into()));
+        self
+    }
+
+    /// Which keys do what. See [`crate::keymap`].
+    pub fn keymap(mut self, keymap: Keymap) -> Self {
+        self.keymap = keymap;
+        self
+    }
+
+    /// The colours to use. See [`crate::theme`].
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Reads keys from `input` and draws to `output`, rather than using `/dev/tty`.
+    /// The finder leaves terminal modes alone in this case, so if they're connected to
+    /// a terminal it's up to you to put it in raw mode first, and the finder won't
+    /// notice it being resized. `input` is read on another thread, which keeps running
+    /// until `input` ends, even after the finder has finished. Only with the `termion`
+    /// feature, as we read keys from `input` the same way.
+    #[cfg(feature = "termion")]
+    pub fn io(mut self, input: impl Read + Send + 'static, output: impl Write + 'static) -> Self {
+        self.io = Some((Box::new(input), Box::new(output)));
+        self
+    }
+
+    /// Which library drives the terminal, if both the `termion` and `crossterm`
+    /// features are on. Termion is used by default.
+    pub fn driver(mut self, driver: Driver) -> Self {
+        self.driver = driver;
+        self
+    }
+
+    /// Reads keys from and draws to `backend`, rather than the terminal. `height` and
+    /// `full_screen` are ignored, as the backend says how much room there is.
+    pub fn backend(mut self, backend: impl Backend + 'static) -> Self {
+        self.backend = Some(Box::new(backend));
+        self
+    }
+}
+
+impl<T> FuzzyFinderBuilder<T>
+where
+    T: Clone,
+{
+    /// Runs the fuzzy finder over `items`. If one is chosen, the outcome is
+    /// [`FindOutcome::Selected`] with its data.
+    pub fn find(self, items: Vec<Item<T>>) -> Result<FindOutcome<T>> {
+        FuzzyFinder::new(items, self, false)?.run()
+    }
+
+    /// Runs the fuzzy finder over `items`, letting the user mark several of them
+    /// with Tab. If any are chosen, the outcome is [`FindOutcome::MultiSelected`] with
+    /// the data of the marked items in the order they were marked, or of the selected
+    /// item if nothing was marked.
+    pub fn find_multi(self, items: Vec<Item<T>>) -> Result<FindOutcome<T>> {
+        FuzzyFinder::new(items, self, true)?.run()
+    }
+
+    /// The finder's state over `items`, without a terminal, so you can drive it
+    /// yourself. See [`crate::state`].
+    pub fn state(self, items: Vec<Item<T>>) -> FinderState<T> {
+        FinderState::new(items, self, false)
+    }
+
+    /// Like [`state`](Self::state), but letting the user mark several items as for
+    /// [`find_multi`](Self::find_multi).
+    pub fn state_multi(self, items: Vec<Item<T>>) -> FinderState<T> {
+        FinderState::new(items, self, true)
+    }
+}
+
+impl<T> FuzzyFinderBuilder<T>
+where
+    T: Clone + Send + 'static,
+{
+    /// Like [`find`](Self::find), but the finder opens straight away and `items` are
+    /// consumed in the background while the user searches. `items` can be any iterator,
+    /// or the receiving end of a channel.
+    pub fn find_stream<I>(self, items: I) -> Result<FindOutcome<T>>
+    where
+        I: IntoIterator<Item = Item<T>> + Send + 'static,
+    {
+        let incoming = stream(items);
+        FuzzyFinder::new(vec![], self, false)?
+            .streaming(incoming)
+            .run()
+    }
+
+    /// Like [`find_multi`](Self::find_multi), but with items consumed in the background
+    /// as for [`find_stream`](Self::find_stream).
+    pub fn find_multi_stream<I>(self, items: I) -> Result<FindOutcome<T>>
+    where
+        I: IntoIterator<Item = Item<T>> + Send + 'static,
+    {
+        let incoming = stream(items);
+        FuzzyFinder::new(vec![], self, true)?
+            .streaming(incoming)
+            .run()
+    }
+}
+
+/// Feeds `items` into a channel from another thread. The thread stops when the
+/// items run out, or when the receiver is dropped.
+fn stream<T, I>(items: I) -> Receiver<Item<T>>
+where
+    T: Send + 'static,
+    I: IntoIterator<Item = Item<T>> + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for item in items {
+            if sender.send(item).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::TestBackend;
+    use crate::keymap::Key;
+    use crate::matcher::PrefixMatcher;
+
+    /// Types `keys`, then `last`.
+    fn find(keys: &str, last: Key) -> FindOutcome<u8> {
+        let backend = TestBackend::new(80, 5);
+        backend.push_str(keys);
+        backend.push_key(last);
+        let items = vec![
+            Item::new(String::from("Frodo"), 1),
+            Item::new(String::from("Sam"), 2),
+        ];
+        FuzzyFinderBuilder::new()
+            .backend(backend)
+            .find(items)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_options() {
+        // GIVEN
+        let items = ["Frodo", "Sam", "Samwise", "Rosie"]
+            .iter()
+            .enumerate()
+            .map(|(i, name)| Item::new(name.to_string(), i))
+            .collect();
+
+        // WHEN
+        let mut state = FuzzyFinderBuilder::new()
+            .prompt("hobbit>")
+            .height(2)
+            .view(ViewStrategy::Fixed)
+            .matcher(PrefixMatcher)
+            .state(items);
+        state.handle_key(Key::Char('s'));
+
+        // THEN
+        assert_eq!(
+            state.frame().lines(),
+            ["   Samwise", ">  Sam", "  2/4", "hobbit> s"]
+        );
+    }
+
+    #[test]
+    fn test_outcomes() {
+        // WHEN
+        let selected = find("sam", Key::Char('\n'));
+        let no_match = find("Gollum", Key::Char('\n'));
+        let aborted = find("fro", Key::Ctrl('c'));
+
+        // THEN
+        assert_eq!(
+            selected,
+            FindOutcome::Selected {
+                item: 2,
+                query: String::from("sam"),
+                key: Key::Char('\n')
+            }
+        );
+        assert_eq!(
+            no_match,
+            FindOutcome::NoMatch {
+                query: String::from("Gollum"),
+                key: Key::Char('\n')
+            }
+        );
+        assert_eq!(
+            aborted,
+            FindOutcome::Aborted {
+                query: String::from("fro")
+            }
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "termion")]
+    fn test_zero_height() {
+        // WHEN
+        let outcome = FuzzyFinderBuilder::new()
+            .height(0)
+            .io(std::io::Cursor::new("\x1B[5;1R\r"), std::io::sink())
+            .find(vec![Item::new(String::from("Frodo"), 1)]);
+
+        // THEN
+        assert!(matches!(outcome, Ok(FindOutcome::Selected { item: 1, .. })));
+    }
+
+    #[test]
+    #[cfg(feature = "termion")]
+    fn test_full_screen() {
+        // GIVEN
+        #[derive(Clone, Default)]
+        struct Screen(std::rc::Rc<std::cell::RefCell<Vec<u8>>>);
+        impl Write for Screen {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.borrow_mut().write(buf)
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        let screen = Screen::default();
+
+        // WHEN
+        let outcome = FuzzyFinderBuilder::new()
+            .full_screen(true)
+            .io(std::io::Cursor::new("\r"), screen.clone())
+            .find(vec![Item::new(String::from("Frodo"), 1)]);
+
+        // THEN
+        assert!(matches!(outcome, Ok(FindOutcome::Selected { item: 1, .. })));
+        let drawn = String::from_utf8(screen.0.take()).unwrap();
+        let entered = drawn
+            .find("\x1B[?1049h")
+            .expect("entered the alternate screen");
+        let frodo = drawn.find("Frodo").unwrap();
+        let left = drawn
+            .rfind("\x1B[?1049l")
+            .expect("went back to the main screen");
+        assert!(entered < frodo && frodo < left);
+    }
+
+    #[test]
+    #[cfg(feature = "termion")]
+    fn test_input_ends() {
+        // WHEN
+        let outcome = FuzzyFinderBuilder::new()
+            .io(std::io::Cursor::new("\x1B[5;1Rfro"), std::io::sink())
+            .find(vec![Item::new(String::from("Frodo"), 1)]);
+
+        // THEN
+        assert!(matches!(
+            outcome,
+            Err(crate::Error::Io(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof
+        ));
+    }
+
+    #[test]
+    #[cfg(not(all(feature = "crossterm", unix)))]
+    fn test_driver_not_built_in() {
+        // WHEN
+        let outcome = FuzzyFinderBuilder::new()
+            .driver(Driver::Crossterm)
+            .find(vec![Item::new(String::from("Frodo"), 1)]);
+
+        // THEN
+        assert!(matches!(outcome, Err(crate::Error::NoBackend)));
+    }
+}
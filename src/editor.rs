@@ -0,0 +1,297 @@
+//! Editing the search term.
+//!
+//! Works like the line editing in a shell: there's a cursor, and text deleted a word
+//! or more at a time goes into a kill buffer that can be pasted back with yank.
+//! Killing several times in a row kills into the buffer together.
+//! The cursor moves over whole graphemes, so e.g. an accent typed as a combining mark
+//! goes with the letter before it.
+use unicode_segmentation::UnicodeSegmentation;
+
+#[derive(Default)]
+pub(crate) struct LineEditor {
+    text: String,
+    /// Byte index into `text`, always on a grapheme boundary.
+    cursor: usize,
+    /// What was last deleted a word or more at a time.
+    killed: String,
+    /// Whether the last edit was a kill, so that the next one adds to `killed` rather
+    /// than replacing it, as in a shell.
+    killing: bool,
+}
+
+impl LineEditor {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The text before the cursor.
+    pub fn before_cursor(&self) -> &str {
+        &self.text[..self.cursor]
+    }
+
+    pub fn insert(&mut self, c: char) {
+        self.killing = false;
+        self.text.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+    }
+
+    pub fn insert_str(&mut self, text: &str) {
+        self.killing = false;
+        self.text.insert_str(self.cursor, text);
+        self.cursor += text.len();
+    }
+
+    pub fn left(&mut self) {
+        self.killing = false;
+        self.cursor = self.prev_char();
+    }
+
+    pub fn right(&mut self) {
+        self.killing = false;
+        self.cursor = self.next_char();
+    }
+
+    pub fn start(&mut self) {
+        self.killing = false;
+        self.cursor = 0;
+    }
+
+    pub fn end(&mut self) {
+        self.killing = false;
+        self.cursor = self.text.len();
+    }
+
+    /// Moves to the start of this word, or of the previous one if we're already there.
+    pub fn word_left(&mut self) {
+        self.killing = false;
+        self.cursor = self.prev_word();
+    }
+
+    /// Moves to the end of this word, or of the next one if we're already there.
+    pub fn word_right(&mut self) {
+        self.killing = false;
+        self.cursor = self.next_word();
+    }
+
+    /// Deletes the grapheme before the cursor.
+    pub fn backspace(&mut self) {
+        self.killing = false;
+        let start = self.prev_char();
+        self.text.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+
+    /// Deletes the grapheme under the cursor.
+    pub fn delete(&mut self) {
+        self.killing = false;
+        let end = self.next_char();
+        self.text.replace_range(self.cursor..end, "");
+    }
+
+    /// Kills back to the previous space, like Ctrl-W in a shell.
+    pub fn kill_word_before(&mut self) {
+        let before = self.before_cursor().trim_end_matches(char::is_whitespace);
+        let start = before
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_whitespace())
+            .map_or(0, |(i, c)| i + c.len_utf8());
+        self.kill(start, self.cursor);
+    }
+
+    /// Kills to the end of the word, like Alt-D in a shell.
+    pub fn kill_word_after(&mut self) {
+        let end = self.next_word();
+        self.kill(self.cursor, end);
+    }
+
+    pub fn kill_to_start(&mut self) {
+        self.kill(0, self.cursor);
+    }
+
+    pub fn kill_to_end(&mut self) {
+        self.kill(self.cursor, self.text.len());
+    }
+
+    /// Inserts whatever was last killed.
+    pub fn yank(&mut self) {
+        let killed = self.killed.clone();
+        self.insert_str(&killed);
+    }
+
+    pub fn clear(&mut self) {
+        self.killing = false;
+        self.text.clear();
+        self.cursor = 0;
+    }
+
+    fn kill(&mut self, start: usize, end: usize) {
+        if !self.killing {
+            self.killed.clear();
+        }
+        self.killing = true;
+        if start < end {
+            let killed = &self.text[start..end];
+            // Text killed backwards goes in front of what was killed before.
+            if end == self.cursor {
+                self.killed.insert_str(0, killed);
+            } else {
+                self.killed.push_str(killed);
+            }
+            self.text.replace_range(start..end, "");
+            self.cursor = start;
+        }
+    }
+
+    fn prev_char(&self) -> usize {
+        self.before_cursor()
+            .grapheme_indices(true)
+            .next_back()
+            .map_or(0, |(i, _)| i)
+    }
+
+    fn next_char(&self) -> usize {
+        self.text[self.cursor..]
+            .graphemes(true)
+            .next()
+            .map_or(self.cursor, |g| self.cursor + g.len())
+    }
+
+    /// Words are runs of letters and digits.
+    fn prev_word(&self) -> usize {
+        let before = self
+            .before_cursor()
+            .trim_end_matches(|c: char| !c.is_alphanumeric());
+        before
+            .char_indices()
+            .rev()
+            .find(|(_, c)| !c.is_alphanumeric())
+            .map_or(0, |(i, c)| i + c.len_utf8())
+    }
+
+    fn next_word(&self) -> usize {
+        let after = &self.text[self.cursor..];
+        let word_start = after.find(char::is_alphanumeric).unwrap_or(after.len());
+        let word_end = after[word_start..]
+            .find(|c: char| !c.is_alphanumeric())
+            .map_or(after.len(), |i| word_start + i);
+        self.cursor + word_end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn editor(text: &str) -> LineEditor {
+        let mut editor = LineEditor::default();
+        editor.insert_str(text);
+        editor
+    }
+
+    #[test]
+    fn test_insert_in_the_middle() {
+        // GIVEN
+        let mut editor = editor("Smwise");
+
+        // WHEN
+        editor.start();
+        editor.right();
+        editor.insert('a');
+
+        // THEN
+        assert_eq!(editor.text(), "Samwise");
+        assert_eq!(editor.before_cursor(), "Sa");
+    }
+
+    #[test]
+    fn test_words() {
+        // GIVEN
+        let mut editor = editor("Bilbo Baggins, of Bag End");
+
+        // WHEN
+        editor.word_left();
+        editor.word_left();
+        let start_of_bag = editor.before_cursor().len();
+        editor.start();
+        editor.word_right();
+        editor.word_right();
+        let end_of_baggins = editor.before_cursor().len();
+
+        // THEN
+        assert_eq!(start_of_bag, 18);
+        assert_eq!(end_of_baggins, 13);
+    }
+
+    #[test]
+    fn test_kill_and_yank() {
+        // GIVEN
+        let mut editor = editor("Bilbo Baggins of Bag End");
+
+        // WHEN
+        editor.kill_word_before();
+        editor.kill_word_before();
+        let killed = editor.text().to_string();
+        editor.start();
+        editor.yank();
+
+        // THEN
+        assert_eq!(killed, "Bilbo Baggins of ");
+        assert_eq!(editor.text(), "Bag EndBilbo Baggins of ");
+    }
+
+    #[test]
+    fn test_kills_in_a_row() {
+        // GIVEN
+        let mut editor = editor("Bilbo Baggins of Bag End");
+
+        // WHEN
+        editor.start();
+        editor.kill_word_after();
+        editor.kill_word_after();
+        editor.end();
+        editor.kill_word_before();
+        editor.yank();
+        let after_moving = editor.text().to_string();
+        editor.kill_word_before();
+        editor.kill_word_before();
+        editor.start();
+        editor.yank();
+        editor.yank();
+
+        // THEN
+        assert_eq!(after_moving, " of Bag End");
+        assert_eq!(editor.text(), "Bag EndBag End of ");
+    }
+
+    #[test]
+    fn test_delete_non_ascii() {
+        // GIVEN
+        let mut editor = editor("Éowyn");
+
+        // WHEN
+        editor.backspace();
+        editor.start();
+        editor.delete();
+
+        // THEN
+        assert_eq!(editor.text(), "owy");
+    }
+
+    #[test]
+    fn test_graphemes() {
+        // GIVEN
+        let mut editor = editor("Nazgu\u{308}l 🧝🏽");
+
+        // WHEN
+        editor.backspace();
+        let without_elf = editor.text().to_string();
+        editor.left();
+        editor.left();
+        editor.backspace();
+
+        // THEN
+        assert_eq!(without_elf, "Nazgu\u{308}l ");
+        assert_eq!(editor.text(), "Nazgl ");
+    }
+}
@@ -0,0 +1,28 @@
+use std::io;
+
+/// What can go wrong while finding.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// There's no terminal to draw on, e.g. when running in the background.
+    #[error("couldn't open the terminal")]
+    NotATty(#[source] io::Error),
+    #[error("couldn't get the size of the terminal")]
+    TerminalSizeUnavailable(#[source] io::Error),
+    /// The terminal didn't say where the cursor is, so we don't know where to draw.
+    #[error("couldn't get the cursor position")]
+    CursorPositionUnavailable(#[source] io::Error),
+    /// A theme given to [`Theme::parse`](crate::theme::Theme::parse) didn't make sense.
+    #[error("{0}")]
+    InvalidTheme(String),
+    /// There's nowhere to draw, as no [`Backend`](crate::backend::Backend) was given
+    /// and the crate was built without the feature for the
+    /// [`Driver`](crate::backend::Driver) chosen, or not for Unix.
+    #[error("no backend given, and the terminal driver isn't built in")]
+    NoBackend,
+    /// Reading from or drawing to the terminal failed.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
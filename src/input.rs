@@ -0,0 +1,425 @@
+//! Turns the bytes the terminal sends into key presses.
+//!
+//! Keys like the arrows arrive as escape sequences, e.g. `ESC [ A` for up. The
+//! escape key by itself is just `ESC`, so when we see one we wait a little while
+//! for the rest of a sequence before deciding it was a lone escape. Different
+//! terminals send different sequences for the same key, so we understand the
+//! xterm, vt100 and rxvt variants.
+use std::time::{Duration, Instant};
+
+use crate::backend::Event;
+use crate::keymap::Key;
+
+/// How long to wait for the rest of an escape sequence. Terminals, and multiplexers
+/// like tmux, normally send a whole sequence at once, so this can be short.
+pub(crate) const ESCAPE_TIMEOUT: Duration = Duration::from_millis(50);
+
+/// What bracketed paste mode wraps pasted text in.
+const PASTE_START: &[u8] = b"\x1b[200~";
+const PASTE_END: &[u8] = b"\x1b[201~";
+
+#[derive(Default)]
+pub(crate) struct Decoder {
+    bytes: Vec<u8>,
+    /// When we started waiting on the bytes at the front of `bytes`.
+    since: Option<Instant>,
+    /// The text pasted so far, while we're in the middle of a paste.
+    paste: Option<Vec<u8>>,
+}
+
+/// The result of decoding the start of some bytes.
+enum Decoded {
+    /// An event, and how many bytes it took up.
+    Event(Event, usize),
+    /// Bytes we don't understand.
+    Skip(usize),
+    /// The start of a paste, and how many bytes its marker took up.
+    PasteStart(usize),
+    /// We need more bytes to know what these are.
+    Incomplete,
+}
+
+impl Decoder {
+    pub fn push(&mut self, bytes: &[u8], now: Instant) {
+        if self.bytes.is_empty() {
+            self.since = Some(now);
+        }
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    /// The next event, if there's a complete one. Call this again after [`ESCAPE_TIMEOUT`]
+    /// if there are leftover bytes, in case they were a lone escape.
+    pub fn next(&mut self, now: Instant) -> Option<Event> {
+        while !self.bytes.is_empty() {
+            if self.paste.is_some() {
+                return self.next_paste();
+            }
+            let timed_out = self
+                .since
+                .is_some_and(|since| now.duration_since(since) >= ESCAPE_TIMEOUT);
+            let (event, len) = match decode(&self.bytes, timed_out) {
+                Decoded::Event(event, len) => (Some(event), len),
+                Decoded::Skip(len) => (None, len),
+                Decoded::PasteStart(len) => {
+                    self.paste = Some(vec![]);
+                    (None, len)
+                }
+                Decoded::Incomplete => return None,
+            };
+            self.bytes.drain(..len);
+            self.since = Some(now);
+            if event.is_some() {
+                return event;
+            }
+        }
+        None
+    }
+
+    fn next_paste(&mut self) -> Option<Event> {
+        let paste = self.paste.as_mut()?;
+        match find(&self.bytes, PASTE_END) {
+            Some(end) => {
+                paste.extend(self.bytes.drain(..end));
+                self.bytes.drain(..PASTE_END.len());
+                let text = String::from_utf8_lossy(paste).into_owned();
+                self.paste = None;
+                Some(Event::Paste(text))
+            }
+            None => {
+                // Hang on to anything that could be the start of the end marker.
+                let keep = (1..PASTE_END.len())
+                    .rev()
+                    .find(|&n| self.bytes.ends_with(&PASTE_END[..n]))
+                    .unwrap_or(0);
+                paste.extend(self.bytes.drain(..self.bytes.len() - keep));
+                None
+            }
+        }
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn key(key: Key, len: usize) -> Decoded {
+    Decoded::Event(Event::Key(key), len)
+}
+
+fn decode(bytes: &[u8], timed_out: bool) -> Decoded {
+    match bytes[0] {
+        0x1b => decode_escape(bytes, timed_out),
+        // Enter sends a carriage return in raw mode, so a line feed must be Ctrl-J
+        b'\r' => key(Key::Char('\n'), 1),
+        b'\t' => key(Key::Char('\t'), 1),
+        // Terminals send one or the other for backspace
+        0x7f | 0x08 => key(Key::Backspace, 1),
+        0 => key(Key::Null, 1),
+        c @ 0x01..=0x1a => key(Key::Ctrl((c - 0x01 + b'a') as char), 1),
+        c @ 0x1c..=0x1f => key(Key::Ctrl((c - 0x1c + b'4') as char), 1),
+        _ => decode_utf8(bytes, timed_out),
+    }
+}
+
+fn decode_utf8(bytes: &[u8], timed_out: bool) -> Decoded {
+    let len = match bytes[0] {
+        0x00..=0x7f => 1,
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => return Decoded::Skip(1),
+    };
+    if bytes.len() < len {
+        return if timed_out {
+            Decoded::Skip(1)
+        } else {
+            Decoded::Incomplete
+        };
+    }
+    match std::str::from_utf8(&bytes[..len]) {
+        Ok(s) => key(Key::Char(s.chars().next().unwrap()), len),
+        Err(_) => Decoded::Skip(1),
+    }
+}
+
+fn decode_escape(bytes: &[u8], timed_out: bool) -> Decoded {
+    if bytes.len() == 1 {
+        return if timed_out {
+            key(Key::Esc, 1)
+        } else {
+            Decoded::Incomplete
+        };
+    }
+    let decoded = match bytes[1] {
+        b'[' => decode_csi(bytes),
+        b'O' => decode_ss3(bytes),
+        0x1b => return key(Key::Esc, 1),
+        // Alt sends escape followed by the key
+        _ => match decode(&bytes[1..], timed_out) {
+            Decoded::Event(Event::Key(Key::Char(c)), len) => key(Key::Alt(c), len + 1),
+            Decoded::Event(Event::Key(Key::Backspace), len) => key(Key::Alt('\x7f'), len + 1),
+            Decoded::Incomplete => Decoded::Incomplete,
+            _ => key(Key::Esc, 1),
+        },
+    };
+    match decoded {
+        // The user probably pressed escape and then `[` or `O`
+        Decoded::Incomplete if timed_out => key(Key::Esc, 1),
+        decoded => decoded,
+    }
+}
+
+/// Decodes `ESC O` sequences, which vt100 and application mode send for some keys.
+fn decode_ss3(bytes: &[u8]) -> Decoded {
+    let len = 3;
+    match bytes.get(2) {
+        None => Decoded::Incomplete,
+        Some(b'A') => key(Key::Up, len),
+        Some(b'B') => key(Key::Down, len),
+        Some(b'C') => key(Key::Right, len),
+        Some(b'D') => key(Key::Left, len),
+        Some(b'H') => key(Key::Home, len),
+        Some(b'F') => key(Key::End, len),
+        Some(b'M') => key(Key::Char('\n'), len),
+        Some(c @ b'P'..=b'S') => key(Key::F(c - b'P' + 1), len),
+        // rxvt's ctrl-arrows
+        Some(b'a') => key(Key::CtrlUp, len),
+        Some(b'b') => key(Key::CtrlDown, len),
+        Some(b'c') => key(Key::CtrlRight, len),
+        Some(b'd') => key(Key::CtrlLeft, len),
+        Some(_) => Decoded::Skip(len),
+    }
+}
+
+/// Decodes `ESC [` sequences, which are how most special keys arrive.
+fn decode_csi(bytes: &[u8]) -> Decoded {
+    // Parameters are digits separated by `;`, and the sequence ends with a letter or
+    // one of a few symbols. rxvt also ends some with `$` to say shift was held.
+    let end = bytes[2..]
+        .iter()
+        .position(|&b| !(b.is_ascii_digit() || b == b';'))
+        .map(|i| i + 2);
+    let end = match end {
+        Some(end) => end,
+        None => return Decoded::Incomplete,
+    };
+    let len = end + 1;
+    if bytes[..len] == *PASTE_START {
+        return Decoded::PasteStart(len);
+    }
+
+    let params: Vec<u16> = std::str::from_utf8(&bytes[2..end])
+        .unwrap_or_default()
+        .split(';')
+        .map(|p| p.parse().unwrap_or(1))
+        .collect();
+    let first = params.first().copied().unwrap_or(1);
+    // xterm says which modifiers were held with a second parameter: 2 is shift, 3 is
+    // alt and 5 is ctrl. Some terminals send 9 for alt.
+    let modifier = params.get(1).copied().unwrap_or(1);
+
+    let arrow = |plain, shift, alt, ctrl| match modifier {
+        2 => key(shift, len),
+        3 | 9 => key(alt, len),
+        5 => key(ctrl, len),
+        _ => key(plain, len),
+    };
+    match bytes[end] {
+        b'A' => arrow(Key::Up, Key::ShiftUp, Key::AltUp, Key::CtrlUp),
+        b'B' => arrow(Key::Down, Key::ShiftDown, Key::AltDown, Key::CtrlDown),
+        b'C' => arrow(Key::Right, Key::ShiftRight, Key::AltRight, Key::CtrlRight),
+        b'D' => arrow(Key::Left, Key::ShiftLeft, Key::AltLeft, Key::CtrlLeft),
+        b'H' if modifier == 5 => key(Key::CtrlHome, len),
+        b'H' => key(Key::Home, len),
+        b'F' if modifier == 5 => key(Key::CtrlEnd, len),
+        b'F' => key(Key::End, len),
+        b'Z' => key(Key::BackTab, len),
+        c @ b'P'..=b'S' => key(Key::F(c - b'P' + 1), len),
+        // rxvt's shift-arrows
+        b'a' => key(Key::ShiftUp, len),
+        b'b' => key(Key::ShiftDown, len),
+        b'c' => key(Key::ShiftRight, len),
+        b'd' => key(Key::ShiftLeft, len),
+        // vt220 style keys, which rxvt follows with `^` for ctrl, `$` for shift and `@`
+        // for both.
+        c @ (b'~' | b'^' | b'$' | b'@') => {
+            let ctrl = c == b'^' || c == b'@' || modifier == 5;
+            match first {
+                1 | 7 if ctrl => key(Key::CtrlHome, len),
+                1 | 7 => key(Key::Home, len),
+                4 | 8 if ctrl => key(Key::CtrlEnd, len),
+                4 | 8 => key(Key::End, len),
+                2 => key(Key::Insert, len),
+                3 => key(Key::Delete, len),
+                5 => key(Key::PageUp, len),
+                6 => key(Key::PageDown, len),
+                n @ 11..=15 => key(Key::F((n - 10) as u8), len),
+                n @ 17..=21 => key(Key::F((n - 11) as u8), len),
+                n @ 23..=24 => key(Key::F((n - 12) as u8), len),
+                _ => Decoded::Skip(len),
+            }
+        }
+        _ => Decoded::Skip(len),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_all(bytes: &[u8]) -> Vec<Event> {
+        let start = Instant::now();
+        let mut decoder = Decoder::default();
+        decoder.push(bytes, start);
+        let mut events = vec![];
+        while let Some(event) = decoder.next(start) {
+            events.push(event);
+        }
+        // Anything left over can only be decided by waiting
+        while let Some(event) = decoder.next(start + ESCAPE_TIMEOUT) {
+            events.push(event);
+        }
+        events
+    }
+
+    fn keys(keys: &[Key]) -> Vec<Event> {
+        keys.iter().map(|&key| Event::Key(key)).collect()
+    }
+
+    #[test]
+    fn test_arrows() {
+        // WHEN
+        let xterm = decode_all(b"\x1b[A\x1b[B\x1b[C\x1b[D");
+        let vt100 = decode_all(b"\x1bOA\x1bOB\x1bOC\x1bOD");
+
+        // THEN
+        let expected = keys(&[Key::Up, Key::Down, Key::Right, Key::Left]);
+        assert_eq!(xterm, expected);
+        assert_eq!(vt100, expected);
+    }
+
+    #[test]
+    fn test_modified_arrows() {
+        // WHEN
+        let xterm = decode_all(b"\x1b[1;5A\x1b[1;3B\x1b[1;2C");
+        let rxvt = decode_all(b"\x1bOa\x1b[b");
+
+        // THEN
+        assert_eq!(xterm, keys(&[Key::CtrlUp, Key::AltDown, Key::ShiftRight]));
+        assert_eq!(rxvt, keys(&[Key::CtrlUp, Key::ShiftDown]));
+    }
+
+    #[test]
+    fn test_home_end_page() {
+        // WHEN
+        let events =
+            decode_all(b"\x1b[H\x1b[F\x1b[1~\x1b[4~\x1b[7~\x1b[8~\x1bOH\x1b[5~\x1b[6~\x1b[3~");
+
+        // THEN
+        assert_eq!(
+            events,
+            keys(&[
+                Key::Home,
+                Key::End,
+                Key::Home,
+                Key::End,
+                Key::Home,
+                Key::End,
+                Key::Home,
+                Key::PageUp,
+                Key::PageDown,
+                Key::Delete
+            ])
+        );
+    }
+
+    #[test]
+    fn test_alt() {
+        // WHEN
+        let events = decode_all(b"\x1bb\x1bf\x1b\x7f");
+
+        // THEN
+        assert_eq!(
+            events,
+            keys(&[Key::Alt('b'), Key::Alt('f'), Key::Alt('\x7f')])
+        );
+    }
+
+    #[test]
+    fn test_lone_escape_waits() {
+        // GIVEN
+        let start = Instant::now();
+        let mut decoder = Decoder::default();
+
+        // WHEN
+        decoder.push(b"\x1b", start);
+        let waiting = decoder.next(start + ESCAPE_TIMEOUT / 2);
+        let timed_out = decoder.next(start + ESCAPE_TIMEOUT);
+
+        // THEN
+        assert_eq!(waiting, None);
+        assert_eq!(timed_out, Some(Event::Key(Key::Esc)));
+    }
+
+    #[test]
+    fn test_split_sequence() {
+        // GIVEN
+        let start = Instant::now();
+        let mut decoder = Decoder::default();
+
+        // WHEN
+        decoder.push(b"\x1b", start);
+        let first = decoder.next(start);
+        decoder.push(b"[A", start);
+        let second = decoder.next(start);
+
+        // THEN
+        assert_eq!(first, None);
+        assert_eq!(second, Some(Event::Key(Key::Up)));
+    }
+
+    #[test]
+    fn test_chars() {
+        // WHEN
+        let events = decode_all("aé日\r\n\t\x7f\x03".as_bytes());
+
+        // THEN
+        assert_eq!(
+            events,
+            keys(&[
+                Key::Char('a'),
+                Key::Char('é'),
+                Key::Char('日'),
+                Key::Char('\n'),
+                Key::Ctrl('j'),
+                Key::Char('\t'),
+                Key::Backspace,
+                Key::Ctrl('c')
+            ])
+        );
+    }
+
+    #[test]
+    fn test_paste() {
+        // GIVEN
+        let start = Instant::now();
+        let mut decoder = Decoder::default();
+
+        // WHEN
+        decoder.push(b"x\x1b[200~Sam\x1b[A", start);
+        let before = decoder.next(start);
+        let incomplete = decoder.next(start);
+        decoder.push(b"wise\x1b[20", start);
+        let still_incomplete = decoder.next(start);
+        decoder.push(b"1~y", start);
+        let paste = decoder.next(start);
+        let after = decoder.next(start);
+
+        // THEN
+        assert_eq!(before, Some(Event::Key(Key::Char('x'))));
+        assert_eq!(incomplete, None);
+        assert_eq!(still_incomplete, None);
+        assert_eq!(paste, Some(Event::Paste(String::from("Sam\x1b[Awise"))));
+        assert_eq!(after, Some(Event::Key(Key::Char('y'))));
+    }
+}
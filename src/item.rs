@@ -13,19 +13,14 @@ impl<T> Item<T> {
     pub fn new(name: String, item: T) -> Self {
         Item::<T> { name, data: item }
     }
-
-    pub fn with_score(self, score: i64, fuzzy_indices: Vec<usize>) -> ScoredItem<T> {
-        ScoredItem {
-            item: self,
-            score,
-            fuzzy_indices,
-        }
-    }
 }
 
+/// An item that matched the search term. It refers to the item rather than holding a
+/// copy, since there are matches for every search term the user has typed.
 #[derive(Clone)]
-pub struct ScoredItem<T> {
-    pub item: Item<T>,
+pub struct ScoredItem {
+    /// The item's position in the list the finder was given.
+    pub index: usize,
     pub score: i64,
     pub fuzzy_indices: Vec<usize>,
 }
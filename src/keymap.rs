@@ -0,0 +1,339 @@
+//! Which keys do what.
+//!
+//! Start from one of the presets and change what you need, then hand the result to
+//! [`FuzzyFinderBuilder::keymap`](crate::FuzzyFinderBuilder::keymap):
+//!
+//! ```no_run
+//! use fuzzy_finder::item::Item;
+//! use fuzzy_finder::keymap::{Action, Key, Keymap};
+//! use fuzzy_finder::FuzzyFinderBuilder;
+//!
+//! let keymap = Keymap::vi()
+//!     .bind(Key::Ctrl('o'), Action::Accept)
+//!     .unbind(Key::Ctrl('d'));
+//! let items = vec![Item::new(String::from("Frodo"), 1)];
+//! let result = FuzzyFinderBuilder::new().keymap(keymap).find(items);
+//! ```
+use std::collections::HashMap;
+
+#[cfg(feature = "crossterm")]
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+
+/// A key, as the finder sees it whichever terminal library the keys came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Key {
+    Backspace,
+    Left,
+    Right,
+    Up,
+    Down,
+    ShiftLeft,
+    ShiftRight,
+    ShiftUp,
+    ShiftDown,
+    AltLeft,
+    AltRight,
+    AltUp,
+    AltDown,
+    CtrlLeft,
+    CtrlRight,
+    CtrlUp,
+    CtrlDown,
+    Home,
+    CtrlHome,
+    End,
+    CtrlEnd,
+    PageUp,
+    PageDown,
+    /// Shift-Tab.
+    BackTab,
+    Delete,
+    Insert,
+    /// A function key, from F1.
+    F(u8),
+    /// A char, including `'\n'` for Enter and `'\t'` for Tab.
+    Char(char),
+    /// A char typed with Alt held. Alt-Backspace is `Alt('\x7f')`.
+    Alt(char),
+    /// A char typed with Ctrl held, always lower case.
+    Ctrl(char),
+    /// Ctrl-Space, which sends a null byte.
+    Null,
+    Esc,
+}
+
+#[cfg(feature = "crossterm")]
+impl Key {
+    /// The key crossterm's describing, if it's one the finder knows. Hand these to
+    /// [`FinderState::handle_key`](crate::state::FinderState::handle_key) when the
+    /// host application reads the keys itself.
+    pub fn from_crossterm(event: KeyEvent) -> Option<Key> {
+        if event.kind == KeyEventKind::Release {
+            return None;
+        }
+        let ctrl = event.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = event.modifiers.contains(KeyModifiers::ALT);
+        let shift = event.modifiers.contains(KeyModifiers::SHIFT);
+        let key = match event.code {
+            KeyCode::Char(' ') if ctrl => Key::Null,
+            // Some terminals send Ctrl-H for backspace
+            KeyCode::Char('h') if ctrl => Key::Backspace,
+            KeyCode::Char(c) if ctrl => Key::Ctrl(c.to_ascii_lowercase()),
+            KeyCode::Char(c) if alt => Key::Alt(c),
+            KeyCode::Char(c) => Key::Char(c),
+            KeyCode::Enter => Key::Char('\n'),
+            KeyCode::Tab => Key::Char('\t'),
+            KeyCode::BackTab => Key::BackTab,
+            KeyCode::Backspace if alt => Key::Alt('\x7f'),
+            KeyCode::Backspace => Key::Backspace,
+            KeyCode::Left if shift => Key::ShiftLeft,
+            KeyCode::Left if alt => Key::AltLeft,
+            KeyCode::Left if ctrl => Key::CtrlLeft,
+            KeyCode::Left => Key::Left,
+            KeyCode::Right if shift => Key::ShiftRight,
+            KeyCode::Right if alt => Key::AltRight,
+            KeyCode::Right if ctrl => Key::CtrlRight,
+            KeyCode::Right => Key::Right,
+            KeyCode::Up if shift => Key::ShiftUp,
+            KeyCode::Up if alt => Key::AltUp,
+            KeyCode::Up if ctrl => Key::CtrlUp,
+            KeyCode::Up => Key::Up,
+            KeyCode::Down if shift => Key::ShiftDown,
+            KeyCode::Down if alt => Key::AltDown,
+            KeyCode::Down if ctrl => Key::CtrlDown,
+            KeyCode::Down => Key::Down,
+            KeyCode::Home if ctrl => Key::CtrlHome,
+            KeyCode::Home => Key::Home,
+            KeyCode::End if ctrl => Key::CtrlEnd,
+            KeyCode::End => Key::End,
+            KeyCode::PageUp => Key::PageUp,
+            KeyCode::PageDown => Key::PageDown,
+            KeyCode::Delete => Key::Delete,
+            KeyCode::Insert => Key::Insert,
+            KeyCode::F(n) => Key::F(n),
+            KeyCode::Null => Key::Null,
+            KeyCode::Esc => Key::Esc,
+            _ => return None,
+        };
+        Some(key)
+    }
+}
+
+/// Something the finder can do in response to a key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    /// Moves the selection up, towards worse matches.
+    Up,
+    /// Moves the selection down, towards the best match.
+    Down,
+    PageUp,
+    PageDown,
+    /// Finishes, choosing the selected or marked items.
+    Accept,
+    /// Finishes without choosing anything.
+    Abort,
+    /// Marks or unmarks the selected item when choosing more than one.
+    ToggleMark,
+    /// Scrolls the selected item left, when its name is too long to show all of it.
+    ScrollLeft,
+    /// Scrolls the selected item right, when its name is too long to show all of it.
+    ScrollRight,
+    CursorLeft,
+    CursorRight,
+    /// Moves the cursor to the start of the search term.
+    CursorStart,
+    /// Moves the cursor to the end of the search term.
+    CursorEnd,
+    /// Moves the cursor to the start of the previous word.
+    WordLeft,
+    /// Moves the cursor to the end of the next word.
+    WordRight,
+    /// Deletes the char before the cursor.
+    DeleteChar,
+    /// Deletes the char under the cursor.
+    DeleteCharForward,
+    /// Deletes back to the previous space. This and the other actions that delete more
+    /// than a char put what they deleted in the kill buffer.
+    DeleteWord,
+    /// Deletes to the end of the word under the cursor.
+    DeleteWordForward,
+    /// Deletes from the start of the search term to the cursor.
+    DeleteToStart,
+    /// Deletes from the cursor to the end of the search term.
+    DeleteToEnd,
+    /// Inserts what's in the kill buffer.
+    Yank,
+    /// Deletes the whole search term.
+    ClearQuery,
+}
+
+/// Maps keys to [`Action`]s. Keys that aren't bound to anything are typed into the
+/// search term if they're chars, and otherwise ignored.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Keymap {
+    bindings: HashMap<Key, Action>,
+}
+
+impl Keymap {
+    /// A keymap with nothing bound, for building your own from scratch.
+    pub fn empty() -> Self {
+        Keymap {
+            bindings: HashMap::new(),
+        }
+    }
+
+    /// The defaults, with emacs's Ctrl-K to delete to the end of the line and Ctrl-V and
+    /// Alt-V for paging. Ctrl-J doesn't move the selection.
+    pub fn emacs() -> Self {
+        Keymap::default()
+            .unbind(Key::Ctrl('j'))
+            .bind(Key::Ctrl('k'), Action::DeleteToEnd)
+            .bind(Key::Ctrl('v'), Action::PageDown)
+            .bind(Key::Alt('v'), Action::PageUp)
+    }
+
+    /// The defaults, with Ctrl-F and Ctrl-B paging down and up as they do in vi, rather
+    /// than moving the cursor. The arrow keys still move it. That's all that changes:
+    /// there's no normal mode, so the search term is always edited as in vi's insert
+    /// mode, where Ctrl-W and Ctrl-U already do what the defaults do.
+    pub fn vi() -> Self {
+        Keymap::default()
+            .bind(Key::Ctrl('f'), Action::PageDown)
+            .bind(Key::Ctrl('b'), Action::PageUp)
+    }
+
+    /// Makes `key` do `action`, replacing whatever it did before.
+    pub fn bind(mut self, key: Key, action: Action) -> Self {
+        self.bindings.insert(key, action);
+        self
+    }
+
+    /// Makes `key` do nothing, or type itself if it's a char.
+    pub fn unbind(mut self, key: Key) -> Self {
+        self.bindings.remove(&key);
+        self
+    }
+
+    /// What `key` is bound to, if anything.
+    pub fn action(&self, key: Key) -> Option<Action> {
+        self.bindings.get(&key).copied()
+    }
+}
+
+/// Much the same as fzf's defaults.
+impl Default for Keymap {
+    fn default() -> Self {
+        use Action::*;
+        let bindings = [
+            (Key::Up, Up),
+            (Key::Ctrl('p'), Up),
+            (Key::Ctrl('k'), Up),
+            (Key::Down, Down),
+            (Key::Ctrl('n'), Down),
+            (Key::Ctrl('j'), Down),
+            (Key::PageUp, PageUp),
+            (Key::PageDown, PageDown),
+            (Key::Char('\n'), Accept),
+            (Key::Esc, Abort),
+            (Key::Ctrl('c'), Abort),
+            (Key::Ctrl('d'), Abort),
+            (Key::Ctrl('g'), Abort),
+            (Key::Char('\t'), ToggleMark),
+            (Key::ShiftLeft, ScrollLeft),
+            (Key::ShiftRight, ScrollRight),
+            (Key::Left, CursorLeft),
+            (Key::Ctrl('b'), CursorLeft),
+            (Key::Right, CursorRight),
+            (Key::Ctrl('f'), CursorRight),
+            (Key::Home, CursorStart),
+            (Key::Ctrl('a'), CursorStart),
+            (Key::End, CursorEnd),
+            (Key::Ctrl('e'), CursorEnd),
+            (Key::Alt('b'), WordLeft),
+            (Key::CtrlLeft, WordLeft),
+            (Key::AltLeft, WordLeft),
+            (Key::Alt('f'), WordRight),
+            (Key::CtrlRight, WordRight),
+            (Key::AltRight, WordRight),
+            (Key::Backspace, DeleteChar),
+            (Key::Delete, DeleteCharForward),
+            (Key::Ctrl('w'), DeleteWord),
+            (Key::Alt('\x7f'), DeleteWord),
+            (Key::Alt('d'), DeleteWordForward),
+            (Key::Ctrl('u'), DeleteToStart),
+            (Key::Ctrl('y'), Yank),
+        ];
+        Keymap {
+            bindings: bindings.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets() {
+        // WHEN
+        let default = Keymap::default();
+        let emacs = Keymap::emacs();
+        let vi = Keymap::vi();
+
+        // THEN
+        assert_eq!(default.action(Key::Ctrl('j')), Some(Action::Down));
+        assert_eq!(emacs.action(Key::Ctrl('j')), None);
+        assert_eq!(emacs.action(Key::Ctrl('k')), Some(Action::DeleteToEnd));
+        assert_eq!(emacs.action(Key::Alt('v')), Some(Action::PageUp));
+        assert_eq!(vi.action(Key::Ctrl('f')), Some(Action::PageDown));
+        assert_eq!(vi.action(Key::Ctrl('n')), Some(Action::Down));
+        let vi_as_default = vi
+            .bind(Key::Ctrl('f'), Action::CursorRight)
+            .bind(Key::Ctrl('b'), Action::CursorLeft);
+        assert_eq!(vi_as_default, default);
+    }
+
+    #[test]
+    fn test_override() {
+        // WHEN
+        let keymap = Keymap::default()
+            .bind(Key::Char('\t'), Action::Down)
+            .unbind(Key::Esc);
+
+        // THEN
+        assert_eq!(keymap.action(Key::Char('\t')), Some(Action::Down));
+        assert_eq!(keymap.action(Key::Esc), None);
+        assert_eq!(keymap.action(Key::Char('a')), None);
+    }
+
+    #[test]
+    #[cfg(feature = "crossterm")]
+    fn test_from_crossterm() {
+        // GIVEN
+        let key = |code, modifiers| Key::from_crossterm(KeyEvent::new(code, modifiers));
+
+        // THEN
+        assert_eq!(
+            key(KeyCode::Enter, KeyModifiers::NONE),
+            Some(Key::Char('\n'))
+        );
+        assert_eq!(
+            key(KeyCode::Char('J'), KeyModifiers::CONTROL),
+            Some(Key::Ctrl('j'))
+        );
+        assert_eq!(
+            key(KeyCode::Char('b'), KeyModifiers::ALT),
+            Some(Key::Alt('b'))
+        );
+        assert_eq!(
+            key(KeyCode::Backspace, KeyModifiers::ALT),
+            Some(Key::Alt('\x7f'))
+        );
+        assert_eq!(
+            key(KeyCode::Left, KeyModifiers::SHIFT),
+            Some(Key::ShiftLeft)
+        );
+        assert_eq!(key(KeyCode::CapsLock, KeyModifiers::NONE), None);
+    }
+}
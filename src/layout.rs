@@ -0,0 +1,161 @@
+//! Fitting item names into the columns we have.
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Shown where a name has been cut off.
+const ELLIPSIS: &str = "…";
+
+/// A grapheme of a name, and where it sits.
+struct Cell<'a> {
+    grapheme: &'a str,
+    /// The column it starts at, counting from the start of the name.
+    column: usize,
+    width: usize,
+    matched: bool,
+}
+
+/// Splits as much of `text` as fits in `width` columns into runs that did and didn't
+/// match. `fuzzy_indices` are char indices, but we highlight whole graphemes, so that
+/// an accent is never coloured differently to its letter.
+///
+/// If `text` is too wide, the ends that are cut off are shown as ellipses, and it's
+/// moved along so the last matched grapheme can be seen. `shift` moves it along by that
+/// many more columns, or back if it's negative. Returns the runs and the shift that was
+/// actually used, which is less if `shift` would go past either end.
+pub(crate) fn clip(
+    text: &str,
+    fuzzy_indices: &[usize],
+    width: usize,
+    shift: isize,
+) -> (Vec<(bool, String)>, isize) {
+    let mut cells = vec![];
+    let mut column = 0;
+    let mut char_index = 0;
+    for grapheme in text.graphemes(true) {
+        let chars = grapheme.chars().count();
+        let matched = (char_index..char_index + chars).any(|i| fuzzy_indices.contains(&i));
+        char_index += chars;
+        cells.push(Cell {
+            grapheme,
+            column,
+            width: grapheme.width(),
+            matched,
+        });
+        column += grapheme.width();
+    }
+    let total = column;
+    if total <= width {
+        return (runs(cells.iter().map(|c| (c.matched, c.grapheme))), 0);
+    }
+    if width < 2 {
+        return (vec![(false, ELLIPSIS.repeat(width))], 0);
+    }
+
+    // Start as far left as we can while keeping the last match in view, leaving room
+    // for an ellipsis at each end.
+    let last_match = cells
+        .iter()
+        .rev()
+        .find(|c| c.matched)
+        .map_or(0, |c| c.column + c.width);
+    let latest_start = total - (width - 1);
+    let automatic = if last_match < width {
+        0
+    } else {
+        (last_match + 2 - width).min(latest_start)
+    };
+    let start = (automatic as isize + shift).clamp(0, latest_start as isize) as usize;
+
+    let mut space = width - usize::from(start > 0);
+    if start + space < total {
+        space -= 1;
+    }
+    let shown = cells
+        .iter()
+        .filter(|c| c.column >= start && c.column + c.width <= start + space)
+        .map(|c| (c.matched, c.grapheme));
+    let left = (start > 0).then_some((false, ELLIPSIS));
+    let right = (start + space < total).then_some((false, ELLIPSIS));
+    let runs = runs(left.into_iter().chain(shown).chain(right));
+    (runs, start as isize - automatic as isize)
+}
+
+/// Joins up neighbouring graphemes that did or didn't match.
+fn runs<'a>(graphemes: impl Iterator<Item = (bool, &'a str)>) -> Vec<(bool, String)> {
+    let mut runs: Vec<(bool, String)> = vec![];
+    for (matched, grapheme) in graphemes {
+        match runs.last_mut() {
+            Some((run_matched, run)) if *run_matched == matched => run.push_str(grapheme),
+            _ => runs.push((matched, grapheme.to_string())),
+        }
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(runs: &[(bool, String)]) -> String {
+        runs.iter().map(|(_, run)| run.as_str()).collect()
+    }
+
+    #[test]
+    fn test_highlighting() {
+        // WHEN
+        let (runs, _) = clip("Jose\u{301} 日本", &[3, 6], 9, 0);
+
+        // THEN
+        assert_eq!(
+            runs,
+            vec![
+                (false, String::from("Jos")),
+                (true, String::from("e\u{301}")),
+                (false, String::from(" ")),
+                (true, String::from("日")),
+                (false, String::from("本")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_truncated() {
+        // WHEN
+        let (runs, _) = clip("src/shire/hobbiton/bag_end.rs", &[0, 1], 12, 0);
+
+        // THEN
+        assert_eq!(text(&runs), "src/shire/h…");
+    }
+
+    #[test]
+    fn test_keeps_last_match_in_view() {
+        // WHEN
+        let (runs, _) = clip("src/shire/hobbiton/bag_end.rs", &[0, 20], 12, 0);
+
+        // THEN
+        assert_eq!(text(&runs), "…obbiton/ba…");
+        assert_eq!(runs[1], (true, String::from("a")));
+    }
+
+    #[test]
+    fn test_shift() {
+        // WHEN
+        let (right, right_shift) = clip("src/shire/hobbiton/bag_end.rs", &[], 12, 100);
+        let (left, left_shift) = clip("src/shire/hobbiton/bag_end.rs", &[20], 12, -100);
+
+        // THEN
+        assert_eq!(text(&right), "…/bag_end.rs");
+        assert_eq!(right_shift, 18);
+        assert_eq!(text(&left), "src/shire/h…");
+        assert_eq!(left_shift, -11);
+    }
+
+    #[test]
+    fn test_wide_chars_at_the_edge() {
+        // WHEN
+        let (runs, _) = clip("指輪物語", &[], 6, 0);
+
+        // THEN
+        assert_eq!(text(&runs), "指輪…");
+    }
+}
@@ -1,335 +1,193 @@
-use anyhow::Result;
-use fuzzy_matcher::skim::SkimMatcherV2;
-use fuzzy_matcher::FuzzyMatcher;
-use item::{Item, ScoredItem};
-use pastel_colours::{
-    BLUE_FG, DARK_BLUE_BG, DARK_GREY_BG, DARK_GREY_FG, GREEN_FG, RESET_BG, RESET_FG,
-};
-use std::io::{stdout, Stdout, Write};
-use std::time::Instant;
-use termion::clear::CurrentLine;
-use termion::cursor::DetectCursorPos;
-use termion::cursor::Show;
-use termion::event::Key;
-use termion::input::TermRead;
-use termion::raw::{IntoRawMode, RawTerminal};
-use view::*;
-
+use backend::{Backend, Event};
+use item::Item;
+pub use outcome::FindOutcome;
+use state::FinderState;
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::time::{Duration, Instant};
+use theme::{ColourDepth, Theme};
+
+pub mod backend;
+pub mod builder;
+mod editor;
+mod error;
+#[cfg(feature = "termion")]
+mod input;
 pub mod item;
+pub mod keymap;
+mod layout;
+pub mod matcher;
+pub mod outcome;
+pub mod preview;
+pub mod query;
+mod search;
+pub mod state;
+#[cfg(any(feature = "termion", all(feature = "crossterm", unix)))]
+mod terminal;
+pub mod theme;
 pub mod view;
-
-pub struct FuzzyFinder<T>
-where
-    T: Clone,
-{
-    search_term: String,
-    all_items: Vec<Item<T>>,
-    matches: Vec<ScoredItem<T>>,
-    console_offset: u16,
-    stdout: RawTerminal<Stdout>,
-    first: bool,
-    view: ScrollingView,
-    positive_space_remaining: u16,
+#[cfg(feature = "ratatui")]
+pub mod widget;
+
+pub use builder::FuzzyFinderBuilder;
+pub use error::{Error, Result};
+#[cfg(feature = "ratatui")]
+pub use widget::FuzzyFinderWidget;
+
+/// Runs a [`FinderState`] on a [`Backend`]: passes on what the user does, and draws
+/// the result.
+pub struct FuzzyFinder<T> {
+    state: FinderState<T>,
+    backend: Box<dyn Backend>,
+    theme: Theme,
+    /// Where items come from when they're streamed in. `None` once they've all arrived.
+    incoming: Option<Receiver<Item<T>>>,
+    drawn: Instant,
 }
 
 impl<T> FuzzyFinder<T>
 where
     T: Clone,
 {
-    fn new(functions: Vec<Item<T>>, lines_to_show: i8) -> Self {
-        // We need to know where to start rendering from. We can't do this later because
-        // we overwrite the cursor. Maybe we shouldn't do this? (TODO)
-        let mut stdout = stdout().into_raw_mode().unwrap();
-
-        write!(stdout, "{}", termion::cursor::Save).unwrap();
-        let mut positive_space_remaining = 0;
-        let console_offset = if stdout.cursor_pos().is_ok() {
-            let cursor_pos_y = stdout.cursor_pos().unwrap().1;
-
-            let terminal_height = termion::terminal_size().unwrap().1;
-            let starting_y = cursor_pos_y;
-            let ending_y = starting_y + lines_to_show as u16;
-            let space_remaining: i16 = terminal_height as i16 - ending_y as i16;
-            positive_space_remaining = if space_remaining < 0 {
-                space_remaining.abs().try_into().unwrap()
-            } else {
-                0
-            };
-            cursor_pos_y
-        } else {
-            log::error!("Cannot get cursor!");
-            0
+    fn new(items: Vec<Item<T>>, mut options: FuzzyFinderBuilder<T>, multi: bool) -> Result<Self> {
+        let backend = match options.backend.take() {
+            Some(backend) => backend,
+            None => terminal(&mut options)?,
         };
-
-        FuzzyFinder {
-            search_term: String::from(""),
-            all_items: functions,
-            matches: vec![],
-            console_offset,
-            stdout,
-            first: true,
-            view: ScrollingView::new(lines_to_show as usize),
-            positive_space_remaining,
-        }
+        let theme = options.theme.for_depth(ColourDepth::detect());
+        let mut state = FinderState::new(items, options, multi);
+        let (width, height) = backend.size();
+        state.resize(width, height);
+
+        Ok(FuzzyFinder {
+            state,
+            backend,
+            theme,
+            incoming: None,
+            drawn: Instant::now(),
+        })
     }
 
-    pub fn up(&mut self) -> Result<()> {
-        self.view.up();
-        self.update_matches();
-        self.render()
+    /// Keep adding items from `incoming` while the user searches.
+    fn streaming(mut self, incoming: Receiver<Item<T>>) -> Self {
+        self.state.set_loading(true);
+        self.incoming = Some(incoming);
+        self
     }
 
-    pub fn down(&mut self) -> Result<()> {
-        self.view.down();
-        self.update_matches();
-        self.render()
-    }
-
-    pub fn append(&mut self, c: char) -> Result<()> {
-        // This is a normal key that we want to add to the search.
-        self.search_term = format!("{}{}", self.search_term, c);
-
-        self.update_matches();
-        self.render()
-    }
-
-    pub fn backspace(&mut self) -> Result<()> {
-        if self.search_term.chars().count() > 0 {
-            self.search_term =
-                String::from(&self.search_term[..self.search_term.chars().count() - 1]);
-        }
-        self.update_matches();
-        self.render()
-    }
-
-    fn render_space(&mut self) -> Result<()> {
-        // Drop down so we don't over-write the terminal line that instigated
-        // this run of lk.
-        write!(self.stdout, "{}", termion::cursor::Save).unwrap();
-        if self.first {
-            for _ in 0..self.view.capacity {
-                writeln!(self.stdout, " ")?;
+    /// Takes whatever items have arrived, without blocking for long. Returns whether
+    /// there's anything new to show.
+    fn receive(&mut self) -> bool {
+        let incoming = match &self.incoming {
+            Some(incoming) => incoming,
+            None => return false,
+        };
+        // Don't let a fast producer starve the UI.
+        let deadline = Instant::now() + Duration::from_millis(20);
+        let mut received = vec![];
+        let mut finished = false;
+        while Instant::now() < deadline {
+            match incoming.try_recv() {
+                Ok(item) => received.push(item),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    finished = true;
+                    break;
+                }
             }
-            self.first = false
         }
-        write!(self.stdout, "{}", termion::cursor::Restore).unwrap();
-
-        Ok(())
-    }
-
-    fn goto_start(&mut self) -> Result<()> {
-        write!(
-            self.stdout,
-            "{}",
-            termion::cursor::Goto(1, self.console_offset - self.positive_space_remaining)
-        )?;
-        Ok(())
-    }
-
-    fn render_items(&mut self) -> Result<()> {
-        self.goto_start()?;
-        // render blank space
-        let list = self.view.render(&self.matches);
-        let num_blank = self.view.capacity - list.len();
-        for _ in 0..num_blank {
-            writeln!(self.stdout, "{}", termion::clear::CurrentLine)?;
+        if finished {
+            self.incoming = None;
+            self.state.set_loading(false);
         }
-        for (is_selected, scored_item) in list {
-            let fuzzy_indices = &scored_item.fuzzy_indices;
-
-            // Do some string manipulation to colourise the indexed parts
-            let coloured_line =
-                get_coloured_line(&fuzzy_indices, &scored_item.item.name, is_selected);
-
-            writeln!(
-                self.stdout,
-                "{}{}{}",
-                termion::clear::CurrentLine,
-                // Go maximum left, so we're at the start of the line
-                termion::cursor::Left(1000),
-                coloured_line
-            )?;
-        }
-        Ok(())
+        let any = !received.is_empty();
+        self.state.extend(received);
+        // Keep the spinner spinning.
+        let spin = self.incoming.is_some() && self.drawn.elapsed() > Duration::from_millis(100);
+        any || finished || spin
     }
 
-    fn render_prompt(&mut self) -> Result<()> {
-        // Render the prompt
-        let prompt_y = self.view.capacity as u16 + 1;
-        let current_x = self.search_term.chars().count() + 2;
-
-        // Go to the bottom line, where we'll render the prompt
-        write!(
-            self.stdout,
-            "{CurrentLine}{}{CurrentLine}",
-            termion::cursor::Goto(current_x as u16, prompt_y + self.console_offset),
-        )?;
-        write!(
-            self.stdout,
-            "{Show}{}{BLUE_FG}${RESET_FG} {}",
-            termion::cursor::Goto(1, prompt_y + self.console_offset),
-            self.search_term,
-        )?;
-        self.stdout.flush()?;
-        Ok(())
+    fn draw(&mut self) -> Result<()> {
+        self.drawn = Instant::now();
+        let frame = self.state.frame();
+        self.backend.draw(&frame, &self.theme)
     }
 
-    /// Gets functions that match our current criteria, sorted by score.
-    pub fn update_matches(&mut self) {
-        self.matches.clear();
-        let matcher = SkimMatcherV2::default();
-        self.matches.extend(self.all_items.iter().flat_map(|f| {
-            let (score, positions) = matcher.fuzzy_indices(&f.name, &self.search_term)?;
-            Some(f.clone().with_score(score, positions))
-        }));
-
-        log::info!(
-            "There are a total of {} item(s) and {} match(es)",
-            self.all_items.len(),
-            self.matches.len()
-        );
-
-        // We want these in the order of their fuzzy matched score, i.e. closed matches
-        self.matches.sort_by(|a, b| b.score.cmp(&a.score));
-    }
-
-    /// Renders the current result set
-    pub fn render(&mut self) -> Result<()> {
-        self.render_space()?;
-        self.render_items()?;
-        self.render_prompt()?;
-        Ok(())
-    }
-
-    /// The main entry point for the fuzzy finder.
+    /// The main entry point for the fuzzy finder, using the default configuration.
+    /// Returns `None` if nothing was chosen, whether or not anything matched.
     pub fn find(items: Vec<Item<T>>, lines_to_show: i8) -> Result<Option<T>> {
-        let mut state = FuzzyFinder::new(items, lines_to_show);
-
-        state.update_matches();
-
-        state.render()?;
-
-        let mut stdin = termion::async_stdin().keys();
+        let outcome = FuzzyFinderBuilder::new()
+            .height(lines_to_show as usize)
+            .find(items)?;
+        Ok(outcome.into_items().into_iter().next())
+    }
 
-        // Run 'sed -n l' to explore escape codes
-        let mut escaped = String::from("");
-        let mut instant = Instant::now();
+    fn run(mut self) -> Result<FindOutcome<T>> {
+        // How long to wait for the user while items or a preview are on their way, and
+        // when they aren't.
+        const LOADING_TIMEOUT: Duration = Duration::from_millis(10);
+        const IDLE_TIMEOUT: Duration = Duration::from_secs(1);
 
+        self.draw()?;
         loop {
-            // What's going on here? The problem is how we detect escape.
-            // The key presses we're interested in, e.g. the arrows, are all preceded by escape, ^[.
-            // E.g. up is ^[[A and down is ^[[B. So the question is how do we identify an escape
-            // key by itself? If it's ^[[A then that's ^[ followed almost instantly by [A. If we have
-            // ^[ followed by a pause then we know it's not an escape for some other key, but an
-            // escape by itself. That's what the 100 136His below.
-            // NB: some terminals might send these bytes too slowly and escape might not be caught.
-            // NB: some terminals might use different escape keys entirely.
-            if escaped == "^[" && instant.elapsed().as_micros() > 100 {
-                write!(state.stdout, "{}", termion::cursor::Restore)?;
-                break;
-            }
-
-            if let Some(Ok(key)) = stdin.next() {
-                match key {
-                    // ctrl-c and ctrl-d are two ways to exit.
-                    Key::Ctrl('c') => break,
-                    Key::Ctrl('d') => break,
-
-                    // NB: It'd be neat if we could use Key::Up and Key::Down but they don't
-                    // work in raw mode. So we've got to deal with the escape codes manually.
-
-                    // This captures the enter key
-                    Key::Char('\n') => {
-                        return if !state.matches.is_empty() {
-                            // Tidy up the console lines we've been writing
-                            for _ in state.console_offset
-                                ..state.console_offset + state.view.capacity as u16 + 4
-                            {
-                                write!(state.stdout, "{}", termion::clear::CurrentLine,)?;
-                            }
-                            Ok(state
-                                .view
-                                .render(&state.matches)
-                                .selected()
-                                .map(|f| f.item.data.to_owned()))
-                        } else {
-                            Ok(None)
-                        };
-                    }
-                    Key::Char(c) => {
-                        if !escaped.is_empty() {
-                            escaped = format!("{}{}", escaped, c);
-                            match escaped.as_str() {
-                                "^[" => continue,
-                                "^[[" => continue,
-                                "^[[A" => {
-                                    escaped = String::from("");
-                                    state.up()?;
-                                }
-                                "^[[B" => {
-                                    escaped = String::from("");
-                                    state.down()?;
-                                }
-                                _ => {
-                                    // This is nothing we recognise so let's abandon the escape sequence.
-                                    escaped = String::from("");
-                                }
-                            }
-                        } else {
-                            state.append(c)?;
-                        }
-                    }
-                    Key::Esc => {
-                        // All we're doing here is recording that we've entered an escape sequence.
-                        // It's actually handled when we handle chars.
-                        if escaped.is_empty() {
-                            escaped = String::from("^[");
-                            instant = Instant::now();
-                        }
+            let timeout = match self.incoming.is_some() || self.state.preview_pending() {
+                true => LOADING_TIMEOUT,
+                false => IDLE_TIMEOUT,
+            };
+            match self.backend.event(timeout)? {
+                Some(Event::Key(key)) => {
+                    // The backend tidies up after us when it's dropped.
+                    if let Some(outcome) = self.state.handle_key(key) {
+                        return Ok(outcome);
                     }
-                    Key::Backspace => {
-                        state.backspace()?;
+                    self.draw()?;
+                }
+                Some(Event::Paste(text)) => {
+                    self.state.paste(&text);
+                    self.draw()?;
+                }
+                Some(Event::Resize) => {
+                    let (width, height) = self.backend.size();
+                    self.state.resize(width, height);
+                    self.draw()?;
+                }
+                None => {
+                    // Nothing's been typed, so there's time to take in new items.
+                    let previewed = self.state.receive_preview();
+                    if self.receive() || previewed {
+                        self.draw()?;
                     }
-                    _ => {}
                 }
-                state.stdout.flush().unwrap();
             }
         }
-        Ok(None)
     }
 }
 
-/// Highlights the line. Will highlight matching search items, and also indicate
-/// if it's a selected item.
-fn get_coloured_line(fuzzy_indecies: &[usize], text: &str, is_selected: bool) -> String {
-    // Do some string manipulation to colourise the indexed parts
-    let mut coloured_line = String::from("");
-    let mut start = 0;
+/// Opens the terminal with the chosen driver, and makes room to draw.
+#[cfg(any(feature = "termion", all(feature = "crossterm", unix)))]
+fn terminal<T>(options: &mut FuzzyFinderBuilder<T>) -> Result<Box<dyn Backend>> {
+    #[cfg(feature = "termion")]
+    let given = options
+        .io
+        .take()
+        .map(|(input, output)| terminal::termion::with_io(input, output));
+    #[cfg(not(feature = "termion"))]
+    let given = None;
+
+    let mut terminal = match (given, options.driver) {
+        (Some(terminal), _) => terminal,
+        #[cfg(feature = "termion")]
+        (None, backend::Driver::Termion) => terminal::termion::tty().map_err(Error::NotATty)?,
+        #[cfg(all(feature = "crossterm", unix))]
+        (None, backend::Driver::Crossterm) => terminal::crossterm::tty().map_err(Error::NotATty)?,
+        // The driver chosen wasn't built in.
+        #[allow(unreachable_patterns)]
+        (None, _) => return Err(Error::NoBackend),
+    };
+    terminal.start(options.full_screen, options.height)?;
+    Ok(Box::new(terminal))
+}
 
-    let text_vec = text.chars().collect::<Vec<_>>();
-    for i in fuzzy_indecies {
-        let part = &text_vec[start..*i].iter().cloned().collect::<String>();
-        let matching_char = &text_vec[*i..*i + 1].iter().cloned().collect::<String>();
-        if is_selected {
-            coloured_line = format!(
-                "{coloured_line}{DARK_GREY_BG}{part}{RESET_BG}{DARK_BLUE_BG}{matching_char}{RESET_BG}"
-            );
-        } else {
-            coloured_line = format!("{coloured_line}{part}{DARK_BLUE_BG}{matching_char}{RESET_BG}");
-        }
-        start = i + 1;
-    }
-    let remaining_chars = &text_vec[start..text.chars().count()]
-        .iter()
-        .cloned()
-        .collect::<String>();
-    if is_selected {
-        let prompt: String = format!("{DARK_GREY_BG}{GREEN_FG}>{RESET_FG}{RESET_BG}",);
-        let spacer: String = format!("{DARK_GREY_FG}  {RESET_FG}");
-        let remaining: String = format!("{DARK_GREY_BG}{remaining_chars}{RESET_BG}");
-        coloured_line = format!("{prompt}{spacer}{coloured_line}{remaining}");
-    } else {
-        coloured_line = format!("{DARK_GREY_BG} {RESET_BG}  {coloured_line}{remaining_chars}");
-    }
-    coloured_line
+#[cfg(not(any(feature = "termion", all(feature = "crossterm", unix))))]
+fn terminal<T>(_options: &mut FuzzyFinderBuilder<T>) -> Result<Box<dyn Backend>> {
+    Err(Error::NoBackend)
 }
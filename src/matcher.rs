@@ -0,0 +1,249 @@
+//! Ways of scoring items against the search term.
+//!
+//! Anything implementing [`Matcher`] can be handed to
+//! [`FuzzyFinderBuilder::matcher`](crate::FuzzyFinderBuilder::matcher).
+use std::collections::HashMap;
+
+use fuzzy_matcher::clangd;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use regex::{Regex, RegexBuilder};
+
+pub trait Matcher: Send + Sync {
+    /// Scores `choice` against `pattern`. Returns `None` if it doesn't match, otherwise
+    /// the score (higher is better) and the indices of the chars in `choice` that matched.
+    fn score(&self, choice: &str, pattern: &str) -> Option<(i64, Vec<usize>)>;
+
+    /// Whether appending to a pattern can only ever remove matches, never add them.
+    /// If so, the finder only re-scores the previous matches as the user types.
+    fn narrows(&self) -> bool {
+        false
+    }
+
+    /// Whether the search term can be split up with fzf's extended syntax, see
+    /// [`crate::query`]. Matchers whose patterns use the same characters for something
+    /// else, like regular expressions, get the whole search term instead.
+    fn supports_extended(&self) -> bool {
+        true
+    }
+
+    /// Called with every pattern in a search term before items are scored against
+    /// them, so that anything slow, like compiling a regex, happens once per search
+    /// rather than once per item.
+    fn prepare(&mut self, _patterns: &[&str]) {}
+}
+
+/// Fuzzy matching as done by skim. This is the default.
+#[derive(Default)]
+pub struct SkimMatcher(SkimMatcherV2);
+
+impl Matcher for SkimMatcher {
+    fn score(&self, choice: &str, pattern: &str) -> Option<(i64, Vec<usize>)> {
+        self.0.fuzzy_indices(choice, pattern)
+    }
+
+    fn narrows(&self) -> bool {
+        true
+    }
+}
+
+/// Fuzzy matching as done by clangd's code completion. Tends to favour matches at
+/// word boundaries, which suits identifiers and file paths.
+#[derive(Default)]
+pub struct ClangdMatcher(clangd::ClangdMatcher);
+
+impl Matcher for ClangdMatcher {
+    fn score(&self, choice: &str, pattern: &str) -> Option<(i64, Vec<usize>)> {
+        self.0.fuzzy_indices(choice, pattern)
+    }
+
+    fn narrows(&self) -> bool {
+        true
+    }
+}
+
+/// Matches items containing the search term exactly. Earlier matches score higher.
+#[derive(Default)]
+pub struct ExactMatcher;
+
+impl Matcher for ExactMatcher {
+    fn score(&self, choice: &str, pattern: &str) -> Option<(i64, Vec<usize>)> {
+        let choice: Vec<char> = choice.chars().collect();
+        let pattern: Vec<char> = pattern.chars().collect();
+        let ignore_case = is_lowercase(&pattern);
+        let start = (0..=choice.len().checked_sub(pattern.len())?)
+            .find(|&start| starts_with(&choice[start..], &pattern, ignore_case))?;
+        Some((-(start as i64), (start..start + pattern.len()).collect()))
+    }
+
+    fn narrows(&self) -> bool {
+        true
+    }
+}
+
+/// Matches items starting with the search term. Shorter items score higher.
+#[derive(Default)]
+pub struct PrefixMatcher;
+
+impl Matcher for PrefixMatcher {
+    fn score(&self, choice: &str, pattern: &str) -> Option<(i64, Vec<usize>)> {
+        let choice: Vec<char> = choice.chars().collect();
+        let pattern: Vec<char> = pattern.chars().collect();
+        if choice.len() < pattern.len() || !starts_with(&choice, &pattern, is_lowercase(&pattern)) {
+            return None;
+        }
+        Some((
+            -((choice.len() - pattern.len()) as i64),
+            (0..pattern.len()).collect(),
+        ))
+    }
+
+    fn narrows(&self) -> bool {
+        true
+    }
+}
+
+/// Treats the search term as a regular expression. Earlier matches score higher.
+/// A search term that isn't a valid regular expression matches nothing. The extended
+/// search syntax doesn't apply, so `^`, `$`, `|` and spaces mean what they do in a regex.
+#[derive(Default)]
+pub struct RegexMatcher {
+    /// The current search's patterns, compiled by [`Matcher::prepare`]. `None` for
+    /// those that aren't valid.
+    compiled: HashMap<String, Option<Regex>>,
+}
+
+impl Matcher for RegexMatcher {
+    fn score(&self, choice: &str, pattern: &str) -> Option<(i64, Vec<usize>)> {
+        let found = match self.compiled.get(pattern) {
+            Some(regex) => regex.as_ref()?.find(choice)?,
+            // Not prepared, e.g. when used on its own, so compile it just this once.
+            None => compile(pattern)?.find(choice)?,
+        };
+        let start = choice[..found.start()].chars().count();
+        let len = found.as_str().chars().count();
+        Some((-(start as i64), (start..start + len).collect()))
+    }
+
+    fn supports_extended(&self) -> bool {
+        false
+    }
+
+    fn prepare(&mut self, patterns: &[&str]) {
+        self.compiled
+            .retain(|pattern, _| patterns.contains(&pattern.as_str()));
+        for pattern in patterns {
+            if !self.compiled.contains_key(*pattern) {
+                self.compiled.insert(pattern.to_string(), compile(pattern));
+            }
+        }
+    }
+}
+
+fn compile(pattern: &str) -> Option<Regex> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(is_lowercase(&pattern.chars().collect::<Vec<_>>()))
+        .build()
+        .ok()
+}
+
+/// Matches items ending with `pattern`. Scores like [`PrefixMatcher`].
+pub(crate) fn suffix_score(choice: &str, pattern: &str) -> Option<(i64, Vec<usize>)> {
+    let choice: Vec<char> = choice.chars().collect();
+    let pattern: Vec<char> = pattern.chars().collect();
+    let start = choice.len().checked_sub(pattern.len())?;
+    if !starts_with(&choice[start..], &pattern, is_lowercase(&pattern)) {
+        return None;
+    }
+    Some((-(start as i64), (start..choice.len()).collect()))
+}
+
+/// Like skim, we only care about case if the pattern has an upper case char in it.
+fn is_lowercase(pattern: &[char]) -> bool {
+    !pattern.iter().any(|c| c.is_uppercase())
+}
+
+/// Whether `text` starts with `pattern`. `text` must be at least as long as `pattern`.
+fn starts_with(text: &[char], pattern: &[char], ignore_case: bool) -> bool {
+    text.iter().zip(pattern).all(|(a, b)| {
+        if ignore_case {
+            a.to_lowercase().eq(b.to_lowercase())
+        } else {
+            a == b
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exact() {
+        // GIVEN
+        let matcher = ExactMatcher;
+
+        // WHEN
+        let result = matcher.score("Samwise Gamgee", "gam");
+
+        // THEN
+        assert_eq!(result, Some((-8, vec![8, 9, 10])));
+        assert_eq!(matcher.score("Samwise Gamgee", "Gandalf"), None);
+        assert_eq!(matcher.score("Samwise Gamgee", "GAM"), None);
+    }
+
+    #[test]
+    fn test_prefix() {
+        // GIVEN
+        let matcher = PrefixMatcher;
+
+        // WHEN
+        let short = matcher.score("Sam", "sa");
+        let long = matcher.score("Samwise", "sa");
+
+        // THEN
+        assert_eq!(short, Some((-1, vec![0, 1])));
+        assert!(long.unwrap().0 < short.unwrap().0);
+        assert_eq!(matcher.score("Frodo", "sa"), None);
+    }
+
+    #[test]
+    fn test_regex() {
+        // GIVEN
+        let matcher = RegexMatcher::default();
+
+        // WHEN
+        let result = matcher.score("Théoden", "o.en$");
+
+        // THEN
+        assert_eq!(result, Some((-3, vec![3, 4, 5, 6])));
+        assert_eq!(matcher.score("Théoden", "("), None);
+    }
+
+    #[test]
+    fn test_regex_prepared() {
+        // GIVEN
+        let mut matcher = RegexMatcher::default();
+
+        // WHEN
+        matcher.prepare(&["^th", "n$", "("]);
+
+        // THEN
+        assert_eq!(matcher.compiled.len(), 3);
+        assert_eq!(matcher.score("Théoden", "^th"), Some((0, vec![0, 1])));
+        assert_eq!(matcher.score("Théoden", "n$"), Some((-6, vec![6])));
+        assert_eq!(matcher.score("Théoden", "("), None);
+    }
+
+    #[test]
+    fn test_non_ascii() {
+        // GIVEN
+        let matcher = ExactMatcher;
+
+        // WHEN
+        let result = matcher.score("Lothlórien", "óri");
+
+        // THEN
+        assert_eq!(result, Some((-5, vec![5, 6, 7])));
+    }
+}
@@ -0,0 +1,57 @@
+//! How a search ended.
+use crate::keymap::Key;
+
+/// What the user did to close the finder, and what they'd typed by then.
+///
+/// `key` is the key that accepted, which is only interesting if you've bound more than
+/// one key to [`Action::Accept`](crate::keymap::Action::Accept).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FindOutcome<T> {
+    /// The selected item was chosen.
+    Selected { item: T, query: String, key: Key },
+    /// Several items were chosen when choosing more than one: the marked items in the
+    /// order they were marked, or the selected item if nothing was marked. Marked items
+    /// are chosen even if they no longer match.
+    MultiSelected {
+        items: Vec<T>,
+        query: String,
+        key: Key,
+    },
+    /// The user gave up, e.g. with Esc or Ctrl-C.
+    Aborted { query: String },
+    /// The user accepted when nothing matched or was marked, so there was nothing to
+    /// choose. The query might be worth using as it is, e.g. as the name of something
+    /// new.
+    NoMatch { query: String, key: Key },
+}
+
+impl<T> FindOutcome<T> {
+    /// The search term when the finder closed.
+    pub fn query(&self) -> &str {
+        match self {
+            FindOutcome::Selected { query, .. }
+            | FindOutcome::MultiSelected { query, .. }
+            | FindOutcome::Aborted { query }
+            | FindOutcome::NoMatch { query, .. } => query,
+        }
+    }
+
+    /// The key that accepted, or `None` if the user gave up.
+    pub fn key(&self) -> Option<Key> {
+        match self {
+            FindOutcome::Selected { key, .. }
+            | FindOutcome::MultiSelected { key, .. }
+            | FindOutcome::NoMatch { key, .. } => Some(*key),
+            FindOutcome::Aborted { .. } => None,
+        }
+    }
+
+    /// Whatever was chosen, which is empty if nothing was.
+    pub fn into_items(self) -> Vec<T> {
+        match self {
+            FindOutcome::Selected { item, .. } => vec![item],
+            FindOutcome::MultiSelected { items, .. } => items,
+            FindOutcome::Aborted { .. } | FindOutcome::NoMatch { .. } => vec![],
+        }
+    }
+}
@@ -0,0 +1,251 @@
+//! A pane next to the matches showing more about the selected item.
+use std::process::Command;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::item::Item;
+
+/// Where the text in the preview pane comes from.
+pub enum Preview<T> {
+    /// Calls the function with the selected item's data.
+    Function(Box<dyn Fn(&T) -> String>),
+    /// Runs the command with `sh -c` and shows what it prints. Any `{}` in the command is
+    /// replaced with the selected item's name, quoted for the shell.
+    Command(String),
+}
+
+/// Works out the preview text, and remembers it until the selection changes.
+pub(crate) struct PreviewPane<T> {
+    preview: Preview<T>,
+    /// The index of the item we last previewed, and its preview.
+    cached: Option<(usize, String)>,
+    /// Runs preview commands, once there's been one to run.
+    worker: Option<Worker>,
+}
+
+impl<T> PreviewPane<T> {
+    pub fn new(preview: Preview<T>) -> Self {
+        PreviewPane {
+            preview,
+            cached: None,
+            worker: None,
+        }
+    }
+
+    /// The preview of `item`, which is at `index` in the finder's items, broken into lines
+    /// that fit in `width` columns. There are never more than `height` lines.
+    ///
+    /// Commands run in the background, and the last preview stays up until they finish.
+    pub fn lines(
+        &mut self,
+        index: usize,
+        item: &Item<T>,
+        width: usize,
+        height: usize,
+    ) -> Vec<String> {
+        self.receive();
+        if self.cached.as_ref().map(|(cached, _)| *cached) != Some(index) {
+            match &self.preview {
+                Preview::Function(function) => self.cached = Some((index, function(&item.data))),
+                Preview::Command(template) => {
+                    let command = template.replace("{}", &shell_quote(&item.name));
+                    let worker = self.worker.get_or_insert_with(Worker::spawn);
+                    worker.request(index, command);
+                }
+            }
+        }
+        let text = self.cached.as_ref().map_or("", |(_, text)| text);
+        wrap(text, width, height)
+    }
+
+    /// Takes in whatever previews the commands have finished. Returns whether there were
+    /// any.
+    pub fn receive(&mut self) -> bool {
+        let Some(worker) = &mut self.worker else {
+            return false;
+        };
+        let mut received = false;
+        while let Ok((index, text)) = worker.results.try_recv() {
+            if worker.pending == Some(index) {
+                worker.pending = None;
+            }
+            self.cached = Some((index, text));
+            received = true;
+        }
+        received
+    }
+
+    /// Whether a command's still working out a preview.
+    pub fn pending(&self) -> bool {
+        self.worker.as_ref().is_some_and(|w| w.pending.is_some())
+    }
+}
+
+/// Runs preview commands on another thread, so that a slow one doesn't stop the finder
+/// responding. It stops when it's dropped.
+struct Worker {
+    requests: Sender<(usize, String)>,
+    results: Receiver<(usize, String)>,
+    /// The item whose preview we've asked for and are still waiting for.
+    pending: Option<usize>,
+}
+
+impl Worker {
+    fn spawn() -> Self {
+        let (requests, incoming) = mpsc::channel::<(usize, String)>();
+        let (outgoing, results) = mpsc::channel();
+        thread::spawn(move || {
+            while let Ok(mut request) = incoming.recv() {
+                // If the selection's moved on while the last command ran, only the
+                // latest item matters.
+                while let Ok(newer) = incoming.try_recv() {
+                    request = newer;
+                }
+                let (index, command) = request;
+                if outgoing.send((index, run(&command))).is_err() {
+                    return;
+                }
+            }
+        });
+        Worker {
+            requests,
+            results,
+            pending: None,
+        }
+    }
+
+    /// Asks for the preview of the item at `index`, unless we already have.
+    fn request(&mut self, index: usize, command: String) {
+        if self.pending != Some(index) && self.requests.send((index, command)).is_ok() {
+            self.pending = Some(index);
+        }
+    }
+}
+
+/// Runs `command` with `sh -c`, and returns what it printed.
+fn run(command: &str) -> String {
+    match Command::new("sh").arg("-c").arg(command).output() {
+        Ok(output) => {
+            let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+            text.push_str(&String::from_utf8_lossy(&output.stderr));
+            text
+        }
+        Err(e) => format!("Could not run `{command}`: {e}"),
+    }
+}
+
+/// Breaks `text` into lines at most `width` columns wide, at spaces where possible. Tabs
+/// become spaces, and other control chars are dropped so they can't mess up the terminal.
+fn wrap(text: &str, width: usize, height: usize) -> Vec<String> {
+    let mut lines = vec![];
+    for line in text.lines() {
+        let line = line.replace('\t', "    ");
+        let graphemes: Vec<&str> = line
+            .graphemes(true)
+            .filter(|g| !g.chars().any(char::is_control))
+            .collect();
+        let mut rest = &graphemes[..];
+        loop {
+            let fits = fitting(rest, width);
+            if fits == rest.len() || lines.len() >= height {
+                break;
+            }
+            match rest[..=fits].iter().rposition(|&g| g == " ") {
+                Some(space) if space > 0 => {
+                    lines.push(rest[..space].concat());
+                    rest = &rest[space + 1..];
+                }
+                _ => {
+                    // Always make progress, even if one grapheme is wider than the pane
+                    let fits = fits.max(1);
+                    lines.push(rest[..fits].concat());
+                    rest = &rest[fits..];
+                }
+            }
+        }
+        lines.push(rest.concat());
+        if lines.len() >= height {
+            break;
+        }
+    }
+    lines.truncate(height);
+    lines
+}
+
+/// How many of `graphemes` fit in `width` columns.
+fn fitting(graphemes: &[&str], width: usize) -> usize {
+    let mut used = 0;
+    graphemes
+        .iter()
+        .take_while(|g| {
+            used += g.width();
+            used <= width
+        })
+        .count()
+}
+
+/// Wraps `text` in single quotes so `sh` takes it literally.
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    #[test]
+    fn test_wrap() {
+        // WHEN
+        let lines = wrap("Frodo Baggins\n\nof\tthe Shire", 6, 5);
+
+        // THEN
+        assert_eq!(lines, vec!["Frodo", "Baggin", "s", "", "of   "]);
+    }
+
+    #[test]
+    fn test_wrap_wide() {
+        // WHEN
+        let lines = wrap("指輪物語", 5, 5);
+
+        // THEN
+        assert_eq!(lines, vec!["指輪", "物語"]);
+    }
+
+    #[test]
+    fn test_command_runs_in_background() {
+        // GIVEN
+        let mut pane = PreviewPane::new(Preview::Command(String::from("sleep 0.1; echo {}")));
+        let frodo = Item::new(String::from("Frodo"), 0);
+
+        // WHEN
+        let before = pane.lines(0, &frodo, 10, 5);
+        let pending = pane.pending();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !pane.receive() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        // THEN
+        assert!(before.is_empty());
+        assert!(pending);
+        assert!(!pane.pending());
+        assert_eq!(pane.lines(0, &frodo, 10, 5), vec!["Frodo"]);
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        // GIVEN
+        let command = "echo {}".replace("{}", &shell_quote("Farmer Maggot's $HOME"));
+
+        // WHEN
+        let output = Command::new("sh").arg("-c").arg(&command).output().unwrap();
+
+        // THEN
+        assert_eq!(output.stdout, b"Farmer Maggot's $HOME\n");
+    }
+}
@@ -0,0 +1,277 @@
+//! fzf's extended search syntax.
+//!
+//! The search term is split on spaces into terms, all of which must match. Terms
+//! separated by `|` are alternatives, only one of which needs to match. Each term
+//! can be modified like this:
+//!
+//! | Term     | Matches items that                   |
+//! |----------|--------------------------------------|
+//! | `foo`    | fuzzy match `foo`                    |
+//! | `'foo`   | contain `foo`                        |
+//! | `^foo`   | start with `foo`                     |
+//! | `foo$`   | end with `foo`                       |
+//! | `^foo$`  | are exactly `foo`                    |
+//! | `!foo`   | don't contain `foo`                  |
+//! | `!^foo`  | don't start with `foo`               |
+//! | `!foo$`  | don't end with `foo`                 |
+//!
+//! A space can be searched for by escaping it with a backslash, e.g. `foo\ bar`.
+use crate::matcher::{self, ExactMatcher, Matcher, PrefixMatcher};
+
+/// A parsed search term: every group must match, and a group matches if any of
+/// its terms do.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Query {
+    groups: Vec<Vec<Term>>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Term {
+    kind: TermKind,
+    negated: bool,
+    text: String,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum TermKind {
+    Fuzzy,
+    Exact,
+    Prefix,
+    Suffix,
+    Equal,
+}
+
+impl Query {
+    /// Parses `search_term` using the extended search syntax.
+    pub fn parse(search_term: &str) -> Self {
+        let mut groups: Vec<Vec<Term>> = vec![];
+        let mut or_next = false;
+        for token in tokenize(search_term) {
+            if token == "|" {
+                or_next = !groups.is_empty();
+                continue;
+            }
+            let term = match Term::parse(&token) {
+                Some(term) => term,
+                None => continue,
+            };
+            match groups.last_mut() {
+                Some(group) if or_next => group.push(term),
+                _ => groups.push(vec![term]),
+            }
+            or_next = false;
+        }
+        Query { groups }
+    }
+
+    /// Treats the whole of `search_term` as a single fuzzy term.
+    pub fn fuzzy(search_term: &str) -> Self {
+        let groups = if search_term.is_empty() {
+            vec![]
+        } else {
+            vec![vec![Term {
+                kind: TermKind::Fuzzy,
+                negated: false,
+                text: search_term.to_string(),
+            }]]
+        };
+        Query { groups }
+    }
+
+    /// The patterns that are handed to the matcher, rather than matched some other way.
+    pub fn patterns(&self) -> Vec<&str> {
+        self.groups
+            .iter()
+            .flatten()
+            .filter(|term| term.kind == TermKind::Fuzzy)
+            .map(|term| term.text.as_str())
+            .collect()
+    }
+
+    /// Scores `choice` against every term. Returns `None` if it doesn't match, otherwise
+    /// the combined score of the matching terms and all the indices they matched.
+    pub fn score(&self, matcher: &dyn Matcher, choice: &str) -> Option<(i64, Vec<usize>)> {
+        let mut total = 0;
+        let mut indices = vec![];
+        for group in &self.groups {
+            let (score, group_indices) = group
+                .iter()
+                .filter_map(|term| term.score(matcher, choice))
+                .max_by_key(|(score, _)| *score)?;
+            total += score;
+            indices.extend(group_indices);
+        }
+        indices.sort_unstable();
+        indices.dedup();
+        Some((total, indices))
+    }
+}
+
+impl Term {
+    fn parse(token: &str) -> Option<Self> {
+        let (negated, text) = match token.strip_prefix('!') {
+            Some(text) => (true, text),
+            None => (false, token),
+        };
+        let (kind, text) = if let Some(text) = text.strip_prefix('\'') {
+            (TermKind::Exact, text)
+        } else {
+            let prefix = text.strip_prefix('^');
+            let suffix = prefix.unwrap_or(text).strip_suffix('$');
+            match (prefix, suffix) {
+                (Some(_), Some(text)) => (TermKind::Equal, text),
+                (Some(text), None) => (TermKind::Prefix, text),
+                (None, Some(text)) => (TermKind::Suffix, text),
+                // Negated terms only make sense as exact matches, as in fzf.
+                (None, None) if negated => (TermKind::Exact, text),
+                (None, None) => (TermKind::Fuzzy, text),
+            }
+        };
+        if text.is_empty() {
+            return None;
+        }
+        Some(Term {
+            kind,
+            negated,
+            text: text.to_string(),
+        })
+    }
+
+    fn score(&self, matcher: &dyn Matcher, choice: &str) -> Option<(i64, Vec<usize>)> {
+        let found = match self.kind {
+            TermKind::Fuzzy => matcher.score(choice, &self.text),
+            TermKind::Exact => ExactMatcher.score(choice, &self.text),
+            TermKind::Prefix => PrefixMatcher.score(choice, &self.text),
+            TermKind::Suffix => matcher::suffix_score(choice, &self.text),
+            TermKind::Equal => PrefixMatcher
+                .score(choice, &self.text)
+                .filter(|(score, _)| *score == 0),
+        };
+        match (found, self.negated) {
+            (Some(_), true) => None,
+            (None, true) => Some((0, vec![])),
+            (found, false) => found,
+        }
+    }
+}
+
+/// Splits on unescaped spaces.
+fn tokenize(search_term: &str) -> Vec<String> {
+    let mut tokens = vec![];
+    let mut token = String::new();
+    let mut chars = search_term.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(' ') => token.push(' '),
+                Some(next) => {
+                    token.push('\\');
+                    token.push(next);
+                }
+                None => token.push('\\'),
+            },
+            ' ' => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            c => token.push(c),
+        }
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matcher::SkimMatcher;
+
+    fn term(kind: TermKind, negated: bool, text: &str) -> Term {
+        Term {
+            kind,
+            negated,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        // WHEN
+        let query = Query::parse("^core go$ | rb$ !'test 'a\\ b ^x$");
+
+        // THEN
+        assert_eq!(
+            query.groups,
+            vec![
+                vec![term(TermKind::Prefix, false, "core")],
+                vec![
+                    term(TermKind::Suffix, false, "go"),
+                    term(TermKind::Suffix, false, "rb")
+                ],
+                vec![term(TermKind::Exact, true, "test")],
+                vec![term(TermKind::Exact, false, "a b")],
+                vec![term(TermKind::Equal, false, "x")],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_incomplete() {
+        // WHEN
+        let query = Query::parse("| ! ' ^ $ foo |");
+
+        // THEN
+        assert_eq!(
+            query.groups,
+            vec![vec![term(TermKind::Fuzzy, false, "foo")]]
+        );
+    }
+
+    #[test]
+    fn test_score_and() {
+        // GIVEN
+        let matcher = SkimMatcher::default();
+        let query = Query::parse("'mw ^s");
+
+        // WHEN
+        let result = query.score(&matcher, "Samwise");
+
+        // THEN
+        assert_eq!(result.map(|(_, indices)| indices), Some(vec![0, 2, 3]));
+        assert_eq!(query.score(&matcher, "Gamwich"), None);
+    }
+
+    #[test]
+    fn test_score_or_and_not() {
+        // GIVEN
+        let matcher = SkimMatcher::default();
+        let query = Query::parse("rn$ | in$ !ara");
+
+        // WHEN
+        let elrond = query.score(&matcher, "Elrond");
+        let arwen = query.score(&matcher, "Arwen");
+        let aragorn = query.score(&matcher, "Aragorn");
+        let celeborn = query.score(&matcher, "Celeborn");
+
+        // THEN
+        assert_eq!(elrond, None);
+        assert_eq!(arwen, None);
+        assert_eq!(aragorn, None);
+        assert_eq!(celeborn.map(|(_, indices)| indices), Some(vec![6, 7]));
+    }
+
+    #[test]
+    fn test_empty() {
+        // GIVEN
+        let matcher = SkimMatcher::default();
+
+        // WHEN
+        let result = Query::parse("").score(&matcher, "Gimli");
+
+        // THEN
+        assert_eq!(result, Some((0, vec![])));
+    }
+}
@@ -0,0 +1,313 @@
+use std::cmp::Reverse;
+
+use crate::item::{Item, ScoredItem};
+use crate::matcher::Matcher;
+use crate::query::Query;
+
+/// Keeps track of which items match the search term.
+///
+/// Typing usually only appends to the search term, which can only ever remove
+/// matches. So where the matcher and query allow it we re-score just the previous
+/// matches rather than every item, and keep the previous results around so that
+/// deleting back to an earlier search term costs nothing.
+pub(crate) struct Search {
+    matcher: Box<dyn Matcher>,
+    extended: bool,
+    /// The search term `matches` are for, if they're up to date.
+    term: Option<String>,
+    matches: Vec<ScoredItem>,
+    /// Results for earlier search terms, each a prefix of the next, most recent last.
+    history: Vec<(String, Vec<ScoredItem>)>,
+}
+
+impl Search {
+    pub fn new(matcher: Box<dyn Matcher>, extended: bool) -> Self {
+        Search {
+            extended: extended && matcher.supports_extended(),
+            matcher,
+            term: None,
+            matches: vec![],
+            history: vec![],
+        }
+    }
+
+    /// The matching items, best first.
+    pub fn matches(&self) -> &[ScoredItem] {
+        &self.matches
+    }
+
+    /// Brings the matches up to date with `search_term`.
+    pub fn update<T>(&mut self, items: &[Item<T>], search_term: &str) {
+        if self.term.as_deref() == Some(search_term) {
+            return;
+        }
+
+        if let Some(term) = self.term.take() {
+            if search_term.starts_with(&term) {
+                self.history.push((term, std::mem::take(&mut self.matches)));
+            }
+        }
+        while let Some((term, _)) = self.history.last() {
+            if search_term.starts_with(term.as_str()) {
+                break;
+            }
+            self.history.pop();
+        }
+
+        let query = self.prepare(search_term);
+        self.matches = match self.history.last() {
+            Some((term, _)) if term == search_term => self.history.pop().unwrap().1,
+            Some((term, matches)) if self.narrows(term, search_term) => {
+                let candidates = matches
+                    .iter()
+                    .map(|m| (m.index, items[m.index].name.as_str()));
+                self.score(candidates.collect(), &query)
+            }
+            _ => {
+                let candidates = items.iter().enumerate().map(|(i, f)| (i, f.name.as_str()));
+                self.score(candidates.collect(), &query)
+            }
+        };
+        self.term = Some(search_term.to_string());
+
+        log::info!(
+            "There are a total of {} item(s) and {} match(es)",
+            items.len(),
+            self.matches.len()
+        );
+    }
+
+    /// Scores the items that have been added since the last update, which start at
+    /// `items[from]`, against the current search term.
+    pub fn extend<T>(&mut self, items: &[Item<T>], from: usize) {
+        let term = match &self.term {
+            Some(term) => term.clone(),
+            None => return,
+        };
+        // Earlier results don't know about the new items.
+        self.history.clear();
+        let query = self.prepare(&term);
+        let candidates = items[from..]
+            .iter()
+            .enumerate()
+            .map(|(i, f)| (from + i, f.name.as_str()));
+        let scored = self.score(candidates.collect(), &query);
+        self.matches = merge(std::mem::take(&mut self.matches), scored);
+    }
+
+    /// Scores the `candidates`, which are indices into the items and their names, and
+    /// returns the ones that match.
+    fn score(&self, candidates: Vec<(usize, &str)>, query: &Query) -> Vec<ScoredItem> {
+        let scored = score_candidates(query, &*self.matcher, candidates);
+        scored
+            .into_iter()
+            .map(|(index, score, fuzzy_indices)| ScoredItem {
+                index,
+                score,
+                fuzzy_indices,
+            })
+            .collect()
+    }
+
+    /// Parses `search_term`, and gets the matcher ready for it.
+    fn prepare(&mut self, search_term: &str) -> Query {
+        let query = if self.extended {
+            Query::parse(search_term)
+        } else {
+            Query::fuzzy(search_term)
+        };
+        self.matcher.prepare(&query.patterns());
+        query
+    }
+
+    /// Whether everything matching `search_term` is guaranteed to be among the matches
+    /// for `previous`, which it starts with.
+    fn narrows(&self, previous: &str, search_term: &str) -> bool {
+        // Appending to these can bring back items that were previously filtered out,
+        // e.g. going from `!fo` to `!foo`, or from `foo |` to `foo | bar`.
+        let widening = |c| matches!(c, '|' | '!' | '$' | '\\');
+        self.matcher.narrows()
+            && search_term.starts_with(previous)
+            && !(self.extended && search_term.contains(widening))
+    }
+}
+
+/// Merges two lists of matches that are each sorted best first. Both are already in
+/// order, so this is much quicker than sorting them all again for every batch of items.
+fn merge(old: Vec<ScoredItem>, new: Vec<ScoredItem>) -> Vec<ScoredItem> {
+    let key = |m: &ScoredItem| (Reverse(m.score), m.index);
+    let mut merged = Vec::with_capacity(old.len() + new.len());
+    let mut old = old.into_iter().peekable();
+    let mut new = new.into_iter().peekable();
+    loop {
+        let next = match (old.peek(), new.peek()) {
+            (Some(a), Some(b)) if key(b) < key(a) => new.next(),
+            (Some(_), _) => old.next(),
+            (None, _) => new.next(),
+        };
+        match next {
+            Some(m) => merged.push(m),
+            None => return merged,
+        }
+    }
+}
+
+/// Scores and sorts the candidates. We want these in the order of their fuzzy
+/// matched score, i.e. closest matches first. Ties keep the order the items were
+/// given in.
+#[cfg(not(feature = "parallel"))]
+fn score_candidates(
+    query: &Query,
+    matcher: &dyn Matcher,
+    candidates: Vec<(usize, &str)>,
+) -> Vec<(usize, i64, Vec<usize>)> {
+    let mut scored: Vec<_> = candidates
+        .into_iter()
+        .filter_map(|(i, name)| {
+            let (score, positions) = query.score(matcher, name)?;
+            Some((i, score, positions))
+        })
+        .collect();
+    scored.sort_unstable_by_key(|(i, score, _)| (Reverse(*score), *i));
+    scored
+}
+
+/// Like the serial version, but scores and sorts on rayon's thread pool. Only the
+/// names are shared between threads, so `T` doesn't need to be `Send` or `Sync`.
+#[cfg(feature = "parallel")]
+fn score_candidates(
+    query: &Query,
+    matcher: &dyn Matcher,
+    candidates: Vec<(usize, &str)>,
+) -> Vec<(usize, i64, Vec<usize>)> {
+    use rayon::prelude::*;
+
+    // Scoring a name is quick, so small chunks would spend more time on coordination.
+    const MIN_CHUNK: usize = 4096;
+    let mut scored: Vec<_> = candidates
+        .into_par_iter()
+        .with_min_len(MIN_CHUNK)
+        .filter_map(|(i, name)| {
+            let (score, positions) = query.score(matcher, name)?;
+            Some((i, score, positions))
+        })
+        .collect();
+    scored.par_sort_unstable_by_key(|(i, score, _)| (Reverse(*score), *i));
+    scored
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matcher::SkimMatcher;
+
+    const NAMES: &[&str] = &[
+        "Aragorn", "Arwen", "Balin", "Barliman", "Bilbo", "Boromir", "Faramir", "Frodo", "Gandalf",
+    ];
+
+    fn items() -> Vec<Item<usize>> {
+        NAMES
+            .iter()
+            .enumerate()
+            .map(|(i, name)| Item::new(name.to_string(), i))
+            .collect()
+    }
+
+    fn names<'a>(search: &Search, items: &'a [Item<usize>]) -> Vec<&'a str> {
+        search
+            .matches()
+            .iter()
+            .map(|m| items[m.index].name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_extend_merges_in_order() {
+        // GIVEN
+        let items = items();
+        let mut search = Search::new(Box::new(SkimMatcher::default()), true);
+        let mut fresh = Search::new(Box::new(SkimMatcher::default()), true);
+        search.update(&items[..4], "ar");
+
+        // WHEN
+        for from in 4..items.len() {
+            search.extend(&items[..=from], from);
+        }
+        fresh.update(&items, "ar");
+
+        // THEN
+        assert_eq!(names(&search, &items), names(&fresh, &items));
+        assert_eq!(search.matches().len(), 4);
+    }
+
+    #[test]
+    fn test_narrowing() {
+        // GIVEN
+        let items = items();
+        let mut search = Search::new(Box::new(SkimMatcher::default()), true);
+        let mut fresh = Search::new(Box::new(SkimMatcher::default()), true);
+
+        // WHEN
+        search.update(&items, "r");
+        search.update(&items, "ra");
+        search.update(&items, "ram");
+        fresh.update(&items, "ram");
+
+        // THEN
+        assert_eq!(search.history.len(), 2);
+        assert_eq!(names(&search, &items), names(&fresh, &items));
+        assert_eq!(names(&search, &items), vec!["Faramir"]);
+    }
+
+    #[test]
+    fn test_backspace_restores() {
+        // GIVEN
+        let items = items();
+        let mut search = Search::new(Box::new(SkimMatcher::default()), true);
+        search.update(&items, "b");
+        let before = names(&search, &items).join(",");
+
+        // WHEN
+        search.update(&items, "bo");
+        search.update(&items, "b");
+
+        // THEN
+        assert_eq!(names(&search, &items).join(","), before);
+        assert!(search.history.is_empty());
+    }
+
+    #[test]
+    fn test_extend() {
+        // GIVEN
+        let items = items();
+        let mut search = Search::new(Box::new(SkimMatcher::default()), true);
+        let mut fresh = Search::new(Box::new(SkimMatcher::default()), true);
+        search.update(&items[..4], "a");
+        search.update(&items[..4], "ar");
+
+        // WHEN
+        search.extend(&items, 4);
+        search.update(&items, "a");
+        fresh.update(&items, "a");
+
+        // THEN
+        assert_eq!(names(&search, &items), names(&fresh, &items));
+    }
+
+    #[test]
+    fn test_widening() {
+        // GIVEN
+        let items = items();
+        let mut search = Search::new(Box::new(SkimMatcher::default()), true);
+        let mut fresh = Search::new(Box::new(SkimMatcher::default()), true);
+
+        // WHEN
+        search.update(&items, "ar !b");
+        search.update(&items, "ar !bo");
+        fresh.update(&items, "ar !bo");
+
+        // THEN
+        assert_eq!(names(&search, &items), names(&fresh, &items));
+        assert!(names(&search, &items).contains(&"Barliman"));
+    }
+}
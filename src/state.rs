@@ -0,0 +1,562 @@
+//! The finder without a terminal.
+//!
+//! [`FinderState`] is everything the finder knows: the items, the search term, what's
+//! selected and marked. It changes in response to keys and [`Action`]s, and describes
+//! what to show as a [`Frame`], but doesn't draw anything itself. That makes it easy to
+//! test how keys behave:
+//!
+//! ```
+//! use fuzzy_finder::item::Item;
+//! use fuzzy_finder::keymap::{Action, Key, Keymap};
+//! use fuzzy_finder::{FindOutcome, FuzzyFinderBuilder};
+//!
+//! let items = vec![
+//!     Item::new(String::from("Frodo"), 1),
+//!     Item::new(String::from("Sam"), 2),
+//! ];
+//! let mut state = FuzzyFinderBuilder::new()
+//!     .keymap(Keymap::default().bind(Key::Ctrl('o'), Action::Accept))
+//!     .state(items);
+//! state.handle_key(Key::Char('s'));
+//! assert_eq!(state.frame().items.len(), 1);
+//!
+//! let outcome = state.handle_key(Key::Ctrl('o'));
+//! assert!(matches!(outcome, Some(FindOutcome::Selected { item: 2, .. })));
+//! ```
+use unicode_width::UnicodeWidthStr;
+
+use crate::editor::LineEditor;
+use crate::item::Item;
+use crate::keymap::{Action, Key, Keymap};
+use crate::layout;
+use crate::preview::PreviewPane;
+use crate::search::Search;
+use crate::view::{AnyView, View};
+use crate::{FindOutcome, FuzzyFinderBuilder};
+
+/// How many columns the selected item scrolls sideways at a time.
+const H_SCROLL_STEP: isize = 8;
+
+/// Shown next to the item count while items are still arriving.
+pub(crate) const SPINNER: &[char] = &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// What the finder knows, apart from how to draw it. Made with
+/// [`FuzzyFinderBuilder::state`].
+pub struct FinderState<T> {
+    search_term: LineEditor,
+    items: Vec<Item<T>>,
+    search: Search,
+    view: AnyView,
+    prompt: String,
+    multi: bool,
+    /// Indices into `items` of the marked items, in the order they were marked.
+    marked: Vec<usize>,
+    preview: Option<PreviewPane<T>>,
+    keymap: Keymap,
+    /// How many columns there are to draw in.
+    width: usize,
+    /// The index of the selected item, and how far it's been scrolled sideways.
+    h_scroll: (usize, isize),
+    /// Whether more items are on their way.
+    loading: bool,
+}
+
+impl<T> FinderState<T>
+where
+    T: Clone,
+{
+    /// Starts out 80 columns wide, with as many rows as the builder's height.
+    pub(crate) fn new(items: Vec<Item<T>>, options: FuzzyFinderBuilder<T>, multi: bool) -> Self {
+        let mut state = FinderState {
+            search_term: LineEditor::default(),
+            items,
+            search: Search::new(options.matcher, options.extended),
+            view: AnyView::new(options.view, options.height.max(1)),
+            prompt: options.prompt,
+            multi,
+            marked: vec![],
+            preview: options.preview.map(PreviewPane::new),
+            keymap: options.keymap,
+            width: 80,
+            h_scroll: (0, 0),
+            loading: false,
+        };
+        state.update_matches();
+        state
+    }
+
+    /// Does whatever `key` is bound to in the keymap, or types it into the search term
+    /// if it's a char that isn't bound to anything. Returns how the search ended if
+    /// that ended it.
+    pub fn handle_key(&mut self, key: Key) -> Option<FindOutcome<T>> {
+        match self.keymap.action(key) {
+            Some(action) => self.apply(action, key),
+            None => {
+                if let Key::Char(c) = key {
+                    self.edit(|line| line.insert(c));
+                }
+                None
+            }
+        }
+    }
+
+    /// Does `action`. `key` is what it was triggered by, which only matters if it
+    /// finishes the search, as it goes in the outcome.
+    pub fn apply(&mut self, action: Action, key: Key) -> Option<FindOutcome<T>> {
+        match action {
+            Action::Abort => {
+                return Some(FindOutcome::Aborted {
+                    query: self.query().to_string(),
+                })
+            }
+            Action::Accept => return Some(self.accept(key)),
+            Action::Up => self.view.up(),
+            Action::Down => self.view.down(),
+            Action::PageUp => self.view.page_up(),
+            Action::PageDown => self.view.page_down(),
+            Action::ToggleMark => self.toggle_mark(),
+            Action::ScrollLeft => self.h_scroll.1 -= H_SCROLL_STEP,
+            Action::ScrollRight => self.h_scroll.1 += H_SCROLL_STEP,
+            Action::CursorLeft => self.edit(LineEditor::left),
+            Action::CursorRight => self.edit(LineEditor::right),
+            Action::CursorStart => self.edit(LineEditor::start),
+            Action::CursorEnd => self.edit(LineEditor::end),
+            Action::WordLeft => self.edit(LineEditor::word_left),
+            Action::WordRight => self.edit(LineEditor::word_right),
+            Action::DeleteChar => self.edit(LineEditor::backspace),
+            Action::DeleteCharForward => self.edit(LineEditor::delete),
+            Action::DeleteWord => self.edit(LineEditor::kill_word_before),
+            Action::DeleteWordForward => self.edit(LineEditor::kill_word_after),
+            Action::DeleteToStart => self.edit(LineEditor::kill_to_start),
+            Action::DeleteToEnd => self.edit(LineEditor::kill_to_end),
+            Action::Yank => self.edit(LineEditor::yank),
+            Action::ClearQuery => self.edit(LineEditor::clear),
+        }
+        None
+    }
+
+    /// Adds pasted text to the search. It's all one line, so line breaks become spaces.
+    pub fn paste(&mut self, text: &str) {
+        let text: String = text
+            .replace(['\r', '\n', '\t'], " ")
+            .chars()
+            .filter(|c| !c.is_control())
+            .collect();
+        self.edit(|line| line.insert_str(&text));
+    }
+
+    /// Fits the finder in `width` columns, with `height` rows for items.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.view.set_capacity(height.max(1));
+    }
+
+    /// Adds items to the end of the list, e.g. as they're streamed in.
+    pub fn extend(&mut self, items: impl IntoIterator<Item = Item<T>>) {
+        let from = self.items.len();
+        self.items.extend(items);
+        if self.items.len() > from {
+            self.search.extend(&self.items, from);
+        }
+    }
+
+    /// Says whether more items are on their way, which is shown next to the count.
+    pub fn set_loading(&mut self, loading: bool) {
+        self.loading = loading;
+    }
+
+    /// Takes in the selected item's preview if its command has finished. Returns
+    /// whether it has, in which case the frame needs drawing again.
+    pub fn receive_preview(&mut self) -> bool {
+        self.preview.as_mut().is_some_and(PreviewPane::receive)
+    }
+
+    /// Whether a preview command is still running, see [`Self::receive_preview`].
+    pub fn preview_pending(&self) -> bool {
+        self.preview.as_ref().is_some_and(PreviewPane::pending)
+    }
+
+    /// The search term.
+    pub fn query(&self) -> &str {
+        self.search_term.text()
+    }
+
+    /// The column the cursor goes in on the prompt line, counting from zero.
+    pub fn cursor_column(&self) -> usize {
+        self.prompt.width() + 1 + self.search_term.before_cursor().width()
+    }
+
+    /// What to show right now.
+    pub fn frame(&mut self) -> Frame {
+        let height = self.view.capacity();
+        // The preview gets the right half of the screen, after a one column border.
+        let (list_width, preview_width) = match self.preview {
+            Some(_) => (
+                self.width.saturating_sub(self.width / 2 + 1),
+                self.width / 2,
+            ),
+            None => (self.width, 0),
+        };
+        let list = self.view.render(self.search.matches());
+        let preview = match (&mut self.preview, list.selected()) {
+            (Some(pane), Some(selected)) => {
+                let item = &self.items[selected.index];
+                Some(pane.lines(selected.index, item, preview_width, height))
+            }
+            (Some(_), None) => Some(vec![]),
+            (None, _) => None,
+        };
+
+        let mut items = vec![];
+        for (selected, scored_item) in list {
+            // Only the selected item can be scrolled sideways
+            let shift = match self.h_scroll {
+                (index, shift) if selected && index == scored_item.index => shift,
+                _ => 0,
+            };
+            let (runs, shift) = layout::clip(
+                &self.items[scored_item.index].name,
+                &scored_item.fuzzy_indices,
+                // Leave room for the pointer and marker
+                list_width.saturating_sub(3),
+                shift,
+            );
+            if selected {
+                self.h_scroll = (scored_item.index, shift);
+            }
+            items.push(FrameItem {
+                runs,
+                selected,
+                marked: self.marked.contains(&scored_item.index),
+            });
+        }
+
+        Frame {
+            height,
+            list_width,
+            items,
+            preview,
+            matched: self.search.matches().len(),
+            total: self.items.len(),
+            marked: self.marked.len(),
+            loading: self.loading,
+            prompt: self.prompt.clone(),
+            query: self.query().to_string(),
+            cursor: self.search_term.before_cursor().width(),
+        }
+    }
+
+    /// Marks the selected item, or unmarks it if it's already marked, then moves
+    /// on to the next item. Does nothing unless we're in multi-select mode.
+    fn toggle_mark(&mut self) {
+        if !self.multi {
+            return;
+        }
+        if let Some(index) = self
+            .view
+            .render(self.search.matches())
+            .selected()
+            .map(|f| f.index)
+        {
+            match self.marked.iter().position(|&i| i == index) {
+                Some(position) => {
+                    self.marked.remove(position);
+                }
+                None => self.marked.push(index),
+            }
+        }
+        self.view.up();
+    }
+
+    /// Finishes, choosing whatever's selected or marked.
+    fn accept(&mut self, key: Key) -> FindOutcome<T> {
+        let query = self.query().to_string();
+        let matched = !self.search.matches().is_empty();
+        // Marked items are chosen even if they don't match what's been typed since.
+        if self.multi && (matched || !self.marked.is_empty()) {
+            FindOutcome::MultiSelected {
+                items: self.chosen(),
+                query,
+                key,
+            }
+        } else if !matched {
+            FindOutcome::NoMatch { query, key }
+        } else {
+            FindOutcome::Selected {
+                item: self.chosen().remove(0),
+                query,
+                key,
+            }
+        }
+    }
+
+    /// The data of the marked items, or of the selected item if nothing is marked.
+    fn chosen(&mut self) -> Vec<T> {
+        if self.marked.is_empty() {
+            self.view
+                .render(self.search.matches())
+                .selected()
+                .map(|f| self.items[f.index].data.to_owned())
+                .into_iter()
+                .collect()
+        } else {
+            self.marked
+                .iter()
+                .map(|&i| self.items[i].data.to_owned())
+                .collect()
+        }
+    }
+
+    /// Changes the search term, or moves the cursor in it.
+    fn edit(&mut self, edit: impl FnOnce(&mut LineEditor)) {
+        edit(&mut self.search_term);
+        self.update_matches();
+    }
+
+    /// Gets items that match our current criteria, sorted by score.
+    fn update_matches(&mut self) {
+        self.search.update(&self.items, self.search_term.text());
+    }
+}
+
+/// Everything there is to show at one moment, laid out but not coloured.
+///
+/// The items go at the bottom of `height` rows, with the best match lowest, then a line
+/// with the number of matches, then the prompt and search term.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    /// How many rows there are for items.
+    pub height: usize,
+    /// How many columns the items get. The preview, if there is one, gets the rest
+    /// after a one column border.
+    pub list_width: usize,
+    /// The matches on screen, top to bottom. There are fewer than `height` if there
+    /// aren't enough matches to fill it.
+    pub items: Vec<FrameItem>,
+    /// The lines of the selected item's preview, if there's a preview pane.
+    pub preview: Option<Vec<String>>,
+    /// How many items match.
+    pub matched: usize,
+    /// How many items there are.
+    pub total: usize,
+    /// How many items are marked.
+    pub marked: usize,
+    /// Whether more items are on their way.
+    pub loading: bool,
+    pub prompt: String,
+    /// The search term.
+    pub query: String,
+    /// How many columns into `query` the cursor is.
+    pub cursor: usize,
+}
+
+/// A match, clipped to fit the width it's shown in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FrameItem {
+    /// The parts of the name that did and didn't match the search term, in order.
+    pub runs: Vec<(bool, String)>,
+    pub selected: bool,
+    pub marked: bool,
+}
+
+impl FrameItem {
+    /// The name as shown, without marking what matched.
+    pub fn text(&self) -> String {
+        self.runs.iter().map(|(_, run)| run.as_str()).collect()
+    }
+}
+
+impl Frame {
+    /// The line with the number of matches, with `spinner` in front of it while items
+    /// are loading.
+    pub fn info(&self, spinner: char) -> String {
+        let spinner = if self.loading { spinner } else { ' ' };
+        let marked = if self.marked == 0 {
+            String::new()
+        } else {
+            format!(" ({})", self.marked)
+        };
+        format!("{spinner} {}/{}{marked}", self.matched, self.total)
+    }
+
+    /// The column the cursor goes in, counting from zero.
+    pub fn cursor_column(&self) -> usize {
+        self.prompt.width() + 1 + self.cursor
+    }
+
+    /// Draws the frame as plain text, a line per row, the way the terminal shows it
+    /// but without colours. Handy for checking what the user would see in tests.
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![String::new(); self.height.saturating_sub(self.items.len())];
+        for item in &self.items {
+            let pointer = if item.selected { '>' } else { ' ' };
+            let marker = if item.marked { '*' } else { ' ' };
+            lines.push(format!("{pointer}{marker} {}", item.text()));
+        }
+        if let Some(preview) = &self.preview {
+            for (row, line) in lines.iter_mut().enumerate() {
+                let padding = self.list_width.saturating_sub(line.width());
+                let text = preview.get(row).map_or("", |line| line.as_str());
+                line.push_str(&format!("{}│{text}", " ".repeat(padding)));
+            }
+        }
+        lines.push(self.info(SPINNER[0]));
+        lines.push(format!("{} {}", self.prompt, self.query));
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matcher::RegexMatcher;
+
+    const HOBBITS: [&str; 4] = ["Frodo", "Sam", "Merry", "Pippin"];
+
+    fn state(multi: bool, keymap: Keymap) -> FinderState<usize> {
+        let items = HOBBITS
+            .iter()
+            .enumerate()
+            .map(|(i, name)| Item::new(name.to_string(), i))
+            .collect();
+        let mut state = FinderState::new(
+            items,
+            FuzzyFinderBuilder::new().height(3).keymap(keymap),
+            multi,
+        );
+        state.resize(20, 3);
+        state
+    }
+
+    fn type_str(state: &mut FinderState<usize>, text: &str) {
+        for c in text.chars() {
+            assert_eq!(state.handle_key(Key::Char(c)), None);
+        }
+    }
+
+    #[test]
+    fn test_typing_narrows_the_frame() {
+        // GIVEN
+        let mut state = state(false, Keymap::default());
+
+        // WHEN
+        type_str(&mut state, "pi");
+        let frame = state.frame();
+
+        // THEN
+        assert_eq!(frame.lines(), vec!["", "", ">  Pippin", "  1/4", "$ pi"]);
+        assert_eq!(frame.items[0].runs[0], (true, String::from("Pi")));
+        assert_eq!(frame.cursor_column(), 4);
+    }
+
+    #[test]
+    fn test_regex_ignores_extended_syntax() {
+        // GIVEN
+        let items = HOBBITS
+            .iter()
+            .enumerate()
+            .map(|(i, name)| Item::new(name.to_string(), i))
+            .collect();
+        let options = FuzzyFinderBuilder::new().matcher(RegexMatcher::default());
+        let mut state = FinderState::new(items, options, false);
+        state.resize(20, 4);
+
+        // WHEN
+        type_str(&mut state, "o.o$");
+        let ending = state.frame();
+        state.apply(Action::ClearQuery, Key::Ctrl('u'));
+        type_str(&mut state, "^(Sam|Merry)$");
+        let either = state.frame();
+
+        // THEN
+        assert_eq!(ending.lines()[3..], [">  Frodo", "  1/4", "$ o.o$"]);
+        assert_eq!(ending.items[0].runs[1], (true, String::from("odo")));
+        assert_eq!(
+            either.lines()[2..],
+            ["   Merry", ">  Sam", "  2/4", "$ ^(Sam|Merry)$"]
+        );
+    }
+
+    #[test]
+    fn test_custom_bindings() {
+        // GIVEN
+        let keymap = Keymap::default()
+            .bind(Key::Char('\t'), Action::Down)
+            .bind(Key::Ctrl('o'), Action::Accept)
+            .unbind(Key::Esc);
+        let mut state = state(false, keymap);
+
+        // WHEN
+        state.handle_key(Key::Up);
+        state.handle_key(Key::Char('\t'));
+        let escaped = state.handle_key(Key::Esc);
+        let outcome = state.handle_key(Key::Ctrl('o'));
+
+        // THEN
+        assert_eq!(escaped, None);
+        assert_eq!(
+            outcome,
+            Some(FindOutcome::Selected {
+                item: 0,
+                query: String::new(),
+                key: Key::Ctrl('o')
+            })
+        );
+    }
+
+    #[test]
+    fn test_marking() {
+        // GIVEN
+        let mut state = state(true, Keymap::default());
+
+        // WHEN
+        state.apply(Action::ToggleMark, Key::Char('\t'));
+        state.apply(Action::ToggleMark, Key::Char('\t'));
+        let lines = state.frame().lines();
+        let outcome = state.apply(Action::Accept, Key::Char('\n'));
+
+        // THEN
+        assert_eq!(
+            lines,
+            vec![">  Merry", " * Sam", " * Frodo", "  4/4 (2)", "$ "]
+        );
+        assert_eq!(outcome.unwrap().into_items(), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_marked_items_survive_no_match() {
+        // GIVEN
+        let mut state = state(true, Keymap::default());
+        state.apply(Action::ToggleMark, Key::Char('\t'));
+        state.apply(Action::ToggleMark, Key::Char('\t'));
+
+        // WHEN
+        type_str(&mut state, "zzz");
+        let outcome = state.apply(Action::Accept, Key::Char('\n'));
+
+        // THEN
+        assert_eq!(
+            outcome,
+            Some(FindOutcome::MultiSelected {
+                items: vec![0, 1],
+                query: String::from("zzz"),
+                key: Key::Char('\n')
+            })
+        );
+    }
+
+    #[test]
+    fn test_resize_keeps_selection() {
+        // GIVEN
+        let mut state = state(false, Keymap::default());
+        state.handle_key(Key::Up);
+        state.handle_key(Key::Up);
+
+        // WHEN
+        state.resize(20, 1);
+        let frame = state.frame();
+
+        // THEN
+        assert_eq!(frame.lines(), vec![">  Merry", "  4/4", "$ "]);
+    }
+}
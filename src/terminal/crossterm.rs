@@ -0,0 +1,100 @@
+//! Terminals driven with crossterm, for applications that already use it and don't want
+//! a second library fighting over raw mode.
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read};
+use std::os::fd::AsRawFd;
+use std::time::{Duration, Instant};
+
+use crossterm::event;
+use crossterm::terminal;
+
+use super::{parse_cursor_pos, Input, Terminal, CURSOR_POS_TIMEOUT};
+use crate::backend::Event;
+use crate::keymap::Key;
+
+/// Opens `/dev/tty` and puts the terminal in raw mode until it's dropped. This works
+/// even if stdin and stdout have been redirected.
+pub(crate) fn tty() -> io::Result<Terminal> {
+    let tty = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+    let output = tty.try_clone()?;
+    let panic_tty = tty.try_clone()?;
+    terminal::enable_raw_mode()?;
+
+    let input = Crossterm { tty };
+    let mut terminal = Terminal::new(Box::new(output), Box::new(input), true);
+    terminal.clean_up_on_panic(panic_tty, || {
+        let _ = terminal::disable_raw_mode();
+    });
+    Ok(terminal)
+}
+
+/// Events from crossterm. Leaves raw mode when dropped.
+struct Crossterm {
+    tty: File,
+}
+
+impl Input for Crossterm {
+    fn size(&self) -> io::Result<(u16, u16)> {
+        terminal::size()
+    }
+
+    fn event(&mut self, timeout: Duration) -> io::Result<Option<Event>> {
+        let deadline = Instant::now() + timeout;
+        while event::poll(deadline.saturating_duration_since(Instant::now()))? {
+            let event = match event::read()? {
+                event::Event::Key(key) => Key::from_crossterm(key).map(Event::Key),
+                event::Event::Paste(text) => Some(Event::Paste(text)),
+                event::Event::Resize(..) => Some(Event::Resize),
+                _ => None,
+            };
+            if event.is_some() {
+                return Ok(event);
+            }
+        }
+        Ok(None)
+    }
+
+    fn cursor_pos(&mut self) -> io::Result<(u16, u16)> {
+        // crossterm would ask on stdout, which might not be the terminal, so we read the
+        // answer ourselves. Anything typed in the meantime is lost.
+        let deadline = Instant::now() + CURSOR_POS_TIMEOUT;
+        let mut received = vec![];
+        while !received.ends_with(b"R") {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            if !wait_for_input(&self.tty, timeout)? {
+                return Err(io::Error::other("Cursor position detection timed out."));
+            }
+            let mut byte = [0];
+            if self.tty.read(&mut byte)? == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            received.push(byte[0]);
+        }
+        parse_cursor_pos(&received).map(|(_, position)| position)
+    }
+}
+
+impl Drop for Crossterm {
+    fn drop(&mut self) {
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Whether there's something to read from `tty` within `timeout`.
+fn wait_for_input(tty: &File, timeout: Duration) -> io::Result<bool> {
+    let mut poll_fd = libc::pollfd {
+        fd: tty.as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    loop {
+        // SAFETY: `poll_fd` is a valid pollfd for an open file descriptor.
+        match unsafe { libc::poll(&mut poll_fd, 1, timeout.as_millis() as libc::c_int) } {
+            n if n < 0 => match io::Error::last_os_error() {
+                e if e.kind() == io::ErrorKind::Interrupted => continue,
+                e => return Err(e),
+            },
+            n => return Ok(n > 0),
+        }
+    }
+}
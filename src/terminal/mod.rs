@@ -0,0 +1,450 @@
+//! The [`Backend`] for a terminal. Drawing is the same whichever library we use, as
+//! it's all escape codes, so only reading keys and raw mode differ between the
+//! `termion` and `crossterm` features.
+use std::fs::File;
+use std::io::{self, Write};
+use std::panic::{self, PanicHookInfo};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::backend::{Backend, Event};
+use crate::state::{Frame, SPINNER};
+use crate::theme::Theme;
+use crate::{Error, Result};
+
+// Reads the cursor position from `/dev/tty` itself, so it's Unix only for now.
+#[cfg(all(feature = "crossterm", unix))]
+pub(crate) mod crossterm;
+#[cfg(feature = "termion")]
+pub(crate) mod termion;
+
+/// How long to wait for the terminal to tell us where the cursor is.
+const CURSOR_POS_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Asks the terminal to mark pasted text, so we can tell it apart from typing.
+const ENABLE_BRACKETED_PASTE: &str = "\x1B[?2004h";
+const DISABLE_BRACKETED_PASTE: &str = "\x1B[?2004l";
+const TO_ALTERNATE_SCREEN: &str = "\x1B[?1049h";
+const TO_MAIN_SCREEN: &str = "\x1B[?1049l";
+const CLEAR_ALL: &str = "\x1B[2J";
+const CLEAR_AFTER_CURSOR: &str = "\x1B[J";
+const CLEAR_LINE: &str = "\x1B[2K";
+const SHOW_CURSOR: &str = "\x1B[?25h";
+
+type PanicHook = dyn Fn(&PanicHookInfo) + Send + Sync;
+
+/// Where a [`Terminal`] finds out what the user's doing, and about the terminal
+/// itself.
+pub(crate) trait Input {
+    /// The size of the terminal as (columns, rows).
+    fn size(&self) -> io::Result<(u16, u16)>;
+
+    /// Waits up to `timeout` for something to happen.
+    fn event(&mut self, timeout: Duration) -> io::Result<Option<Event>>;
+
+    /// Waits for the answer to the request for the cursor position we've just sent, as
+    /// 1-based (column, row).
+    fn cursor_pos(&mut self) -> io::Result<(u16, u16)>;
+}
+
+/// The [`Backend`] for a terminal.
+///
+/// Whichever way we finish, dropping it clears away what we drew, shows the cursor
+/// and leaves raw mode, so the terminal is left as we found it.
+pub(crate) struct Terminal {
+    output: Box<dyn Write>,
+    /// What we've drawn but not flushed yet. We keep this ourselves rather than using a
+    /// `BufWriter`, so that it can be thrown away if a panic's already tidied up.
+    buffer: Vec<u8>,
+    input: Box<dyn Input>,
+    /// Whether we opened the terminal ourselves, rather than being given somewhere to
+    /// draw.
+    is_tty: bool,
+    /// What to clear up when we're done.
+    cleanup: Arc<Mutex<Cleanup>>,
+    /// The panic hook that was there before we put in ours.
+    previous_hook: Option<Arc<PanicHook>>,
+    /// The row the first item is drawn on. The info line and prompt go below the items.
+    top: u16,
+    /// How many items we were asked to show, if there's room.
+    height: usize,
+    full_screen: bool,
+    /// The columns, and rows for items, that we've made room for.
+    size: (usize, usize),
+    /// When we started, to time the spinner.
+    started: Instant,
+}
+
+impl Terminal {
+    fn new(mut output: Box<dyn Write>, input: Box<dyn Input>, is_tty: bool) -> Self {
+        // If this fails we'll find out when we next draw
+        let _ = write!(output, "{ENABLE_BRACKETED_PASTE}");
+        Terminal {
+            output,
+            buffer: vec![],
+            input,
+            is_tty,
+            cleanup: Arc::default(),
+            previous_hook: None,
+            top: 1,
+            height: 1,
+            full_screen: false,
+            size: (80, 1),
+            started: Instant::now(),
+        }
+    }
+
+    /// Makes room to draw in: all of the alternate screen if `full_screen`, or otherwise
+    /// `height` rows for items below the cursor, or as many as fit.
+    pub fn start(&mut self, full_screen: bool, height: usize) -> Result<()> {
+        self.full_screen = full_screen;
+        self.height = height;
+        // If we were given somewhere to draw rather than opening the terminal ourselves,
+        // it might not be a terminal at all, so we make do.
+        let (width, rows) = match self.input.size() {
+            Ok(size) => size,
+            Err(e) if self.is_tty => return Err(Error::TerminalSizeUnavailable(e)),
+            Err(_) => (80, 24),
+        };
+        // Leave room for the info line and the prompt
+        let space = (rows as usize).saturating_sub(2).max(1);
+
+        let capacity = if full_screen {
+            self.enter_alternate_screen()?;
+            space
+        } else {
+            // We need to know where to start rendering from. We can't do this later
+            // because we overwrite the cursor.
+            let row = match self.cursor_pos() {
+                Ok((_, row)) => row,
+                Err(e) if self.is_tty => return Err(Error::CursorPositionUnavailable(e)),
+                Err(e) => {
+                    log::error!("Cannot get cursor! {e}");
+                    1
+                }
+            };
+            // Make room below the cursor, which scrolls the terminal if we're near the
+            // bottom.
+            let capacity = height.min(space).max(1);
+            for _ in 0..capacity + 1 {
+                writeln!(self)?;
+            }
+            self.set_top(row.min(rows.saturating_sub(capacity as u16 + 1).max(1)));
+            capacity
+        };
+        self.size = (width as usize, capacity);
+        Ok(())
+    }
+
+    /// Makes room again to fit the terminal's new size, clearing what we'd drawn.
+    fn resize(&mut self) -> Result<()> {
+        let (width, rows) = self.input.size()?;
+        let space = (rows as usize).saturating_sub(2).max(1);
+        let capacity = if self.full_screen {
+            write!(self, "{CLEAR_ALL}")?;
+            space
+        } else {
+            // The terminal might have scrolled what we'd drawn, but the cursor moves
+            // with it, and we always leave the cursor on the prompt.
+            let old_capacity = self.size.1 as u16;
+            if let Ok((_, prompt_row)) = self.cursor_pos() {
+                self.top = prompt_row.saturating_sub(old_capacity + 1).max(1);
+            }
+            let capacity = self.height.min(space).max(1);
+            self.set_top(
+                self.top
+                    .min(rows.saturating_sub(capacity as u16 + 1))
+                    .max(1),
+            );
+            write!(self, "{}{CLEAR_AFTER_CURSOR}", goto(1, self.top))?;
+            capacity
+        };
+        self.size = (width as usize, capacity);
+        Ok(())
+    }
+
+    /// Switches to the alternate screen, which leaves what was on screen untouched
+    /// until we switch back when we're dropped.
+    fn enter_alternate_screen(&mut self) -> io::Result<()> {
+        write!(self.output, "{TO_ALTERNATE_SCREEN}{CLEAR_ALL}")?;
+        lock(&self.cleanup).alternate = true;
+        Ok(())
+    }
+
+    /// Tells us the first row we're drawing on, when we're drawing below the cursor
+    /// rather than on the alternate screen. Everything from there down is cleared when
+    /// we're done.
+    fn set_top(&mut self, top: u16) {
+        self.top = top;
+        lock(&self.cleanup).top = Some(top);
+    }
+
+    /// Asks the terminal where the cursor is, as 1-based (column, row).
+    fn cursor_pos(&mut self) -> io::Result<(u16, u16)> {
+        write!(self, "\x1B[6n")?;
+        self.flush()?;
+        self.input.cursor_pos()
+    }
+
+    /// Puts in a panic hook that clears up the terminal before the panic message is
+    /// printed, calling `restore` to leave raw mode. Otherwise the message would be
+    /// printed in raw mode, and then cleared away with everything else we drew.
+    fn clean_up_on_panic(&mut self, tty: File, restore: impl Fn() + Send + Sync + 'static) {
+        let previous: Arc<PanicHook> = Arc::from(panic::take_hook());
+        let ours = Arc::clone(&previous);
+        let cleanup = Arc::clone(&self.cleanup);
+        // Panics on other threads, e.g. while scoring items, don't stop us drawing.
+        let finder_thread = thread::current().id();
+        panic::set_hook(Box::new(move |info| {
+            if thread::current().id() == finder_thread {
+                let mut cleanup = lock(&cleanup);
+                if !cleanup.done {
+                    let _ = write!(&tty, "{}", cleanup.codes());
+                    restore();
+                    cleanup.done = true;
+                }
+            }
+            ours(info);
+        }));
+        self.previous_hook = Some(previous);
+    }
+}
+
+impl Backend for Terminal {
+    fn size(&self) -> (usize, usize) {
+        self.size
+    }
+
+    fn event(&mut self, timeout: Duration) -> Result<Option<Event>> {
+        let event = self.input.event(timeout)?;
+        if event == Some(Event::Resize) {
+            self.resize()?;
+        }
+        Ok(event)
+    }
+
+    fn draw(&mut self, frame: &Frame, theme: &Theme) -> Result<()> {
+        write!(self, "{}", goto(1, self.top))?;
+        let mut lines = vec![String::new(); frame.height.saturating_sub(frame.items.len())];
+        lines.extend(frame.items.iter().map(|item| theme.paint_item(item)));
+        for (row, line) in lines.iter().enumerate() {
+            // Go maximum left, so we're at the start of the line
+            write!(self, "{CLEAR_LINE}\x1B[1000D{line}")?;
+            if let Some(preview) = &frame.preview {
+                let text = preview.get(row).map_or("", |line| line.as_str());
+                write!(
+                    self,
+                    "\x1B[1000D\x1B[{}C{}{text}",
+                    frame.list_width,
+                    theme.border.fg("│"),
+                )?;
+            }
+            writeln!(self)?;
+        }
+
+        let frame_number = self.started.elapsed().as_millis() / 100;
+        let spinner = SPINNER[frame_number as usize % SPINNER.len()];
+        // Raw mode doesn't return to the start of the line after a newline
+        let info_y = self.top + frame.height as u16;
+        write!(
+            self,
+            "{}{CLEAR_LINE}{}",
+            goto(1, info_y),
+            theme.info.fg(&frame.info(spinner))
+        )?;
+
+        // The prompt goes on the bottom line, under the info line
+        let prompt_y = self.top + frame.height as u16 + 1;
+        write!(
+            self,
+            "{}{CLEAR_LINE}{SHOW_CURSOR}{} {}{}",
+            goto(1, prompt_y),
+            theme.prompt.fg(&frame.prompt),
+            frame.query,
+            goto(frame.cursor_column() as u16 + 1, prompt_y),
+        )?;
+        self.flush()?;
+        Ok(())
+    }
+}
+
+impl Write for Terminal {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.output.write_all(&self.buffer)?;
+        self.buffer.clear();
+        self.output.flush()
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let mut cleanup = lock(&self.cleanup);
+        // If the panic hook has already tidied up, drawing anything more would only
+        // make a mess.
+        if !cleanup.done {
+            self.buffer.extend_from_slice(cleanup.codes().as_bytes());
+            let _ = self.output.write_all(&self.buffer);
+            let _ = self.output.flush();
+            cleanup.done = true;
+        }
+        drop(cleanup);
+        // Raw mode is left when `input` and `output` are dropped.
+
+        // Putting back the previous hook itself panics if we're panicking, and ours
+        // does nothing now anyway.
+        if let Some(previous) = self.previous_hook.take() {
+            if !thread::panicking() {
+                panic::set_hook(Box::new(move |info| previous(info)));
+            }
+        }
+    }
+}
+
+/// What needs clearing up when we're done.
+#[derive(Default)]
+struct Cleanup {
+    /// The first row we drew on, if we drew below the cursor.
+    top: Option<u16>,
+    /// Whether we switched to the alternate screen.
+    alternate: bool,
+    done: bool,
+}
+
+impl Cleanup {
+    /// Escape codes that clear what we drew and put the cursor back where it was.
+    fn codes(&self) -> String {
+        let mut codes = String::new();
+        if self.alternate {
+            codes.push_str(TO_MAIN_SCREEN);
+        } else if let Some(top) = self.top {
+            codes.push_str(&format!("{}{CLEAR_AFTER_CURSOR}", goto(1, top)));
+        }
+        codes.push_str(&format!("{SHOW_CURSOR}{DISABLE_BRACKETED_PASTE}"));
+        codes
+    }
+}
+
+/// Moves the cursor to the 1-based `column` and `row`.
+fn goto(column: u16, row: u16) -> String {
+    format!("\x1B[{row};{column}H")
+}
+
+/// We don't hold the lock while doing anything that could panic, but if we somehow
+/// did, clearing up is still worth a try.
+fn lock(cleanup: &Mutex<Cleanup>) -> std::sync::MutexGuard<'_, Cleanup> {
+    cleanup.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Finds the terminal's answer to a request for the cursor position at the end of
+/// `received`, which looks like `ESC [ row ; column R`. Returns where the answer starts,
+/// as anything before it was typed in the meantime, and the 1-based (column, row).
+fn parse_cursor_pos(received: &[u8]) -> io::Result<(usize, (u16, u16))> {
+    let invalid = || io::Error::other("Could not parse the cursor position.");
+    let start = received
+        .windows(2)
+        .rposition(|w| w == b"\x1B[")
+        .ok_or_else(invalid)?;
+    let answer = received[start + 2..]
+        .strip_suffix(b"R")
+        .ok_or_else(invalid)?;
+    let answer = String::from_utf8_lossy(answer);
+
+    let parse = |n: Option<&str>| n.and_then(|n| n.parse::<u16>().ok());
+    let mut numbers = answer.split(';');
+    match (parse(numbers.next()), parse(numbers.next())) {
+        (Some(row), Some(column)) => Ok((start, (column, row))),
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    /// Somewhere to draw that we can look at afterwards.
+    #[derive(Clone, Default)]
+    struct Screen(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Screen {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// A terminal where nothing happens.
+    struct Quiet;
+
+    impl Input for Quiet {
+        fn size(&self) -> io::Result<(u16, u16)> {
+            Ok((80, 24))
+        }
+
+        fn event(&mut self, _timeout: Duration) -> io::Result<Option<Event>> {
+            Ok(None)
+        }
+
+        fn cursor_pos(&mut self) -> io::Result<(u16, u16)> {
+            Ok((1, 1))
+        }
+    }
+
+    #[test]
+    fn test_clears_up_when_dropped() {
+        // GIVEN
+        let screen = Screen::default();
+        let mut terminal = Terminal::new(Box::new(screen.clone()), Box::new(Quiet), false);
+        terminal.set_top(7);
+
+        // WHEN
+        write!(terminal, "Gandalf").unwrap();
+        drop(terminal);
+
+        // THEN
+        let drawn = String::from_utf8(screen.0.take()).unwrap();
+        assert!(drawn.ends_with(&format!(
+            "Gandalf\x1B[7;1H{CLEAR_AFTER_CURSOR}{SHOW_CURSOR}{DISABLE_BRACKETED_PASTE}"
+        )));
+    }
+
+    #[test]
+    fn test_info_line_starts_at_the_left() {
+        // GIVEN
+        let screen = Screen::default();
+        let mut terminal = Terminal::new(Box::new(screen.clone()), Box::new(Quiet), false);
+        terminal.set_top(3);
+        let items = vec![crate::item::Item::new(String::from("Frodo"), 1)];
+        let mut state = crate::FuzzyFinderBuilder::new().state(items);
+        state.resize(20, 2);
+
+        // WHEN
+        terminal.draw(&state.frame(), &Theme::no_colour()).unwrap();
+
+        // THEN
+        let drawn = String::from_utf8(screen.0.take()).unwrap();
+        assert!(drawn.contains(&format!("\x1B[5;1H{CLEAR_LINE}  1/1")));
+    }
+
+    #[test]
+    fn test_parse_cursor_pos() {
+        // WHEN
+        let typed = parse_cursor_pos(b"ab\x1B[12;5R").unwrap();
+        let garbled = parse_cursor_pos(b"\x1B[12R");
+
+        // THEN
+        assert_eq!(typed, (2, (5, 12)));
+        assert!(garbled.is_err());
+    }
+}
@@ -0,0 +1,208 @@
+//! Terminals driven with termion. Keys are read as bytes on another thread, so that
+//! the finder can carry on while the user isn't typing, and decoded ourselves.
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::fd::AsRawFd;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use signal_hook::consts::SIGWINCH;
+use signal_hook::SigId;
+use termion::raw::IntoRawMode;
+
+use super::{parse_cursor_pos, Input, Terminal, CURSOR_POS_TIMEOUT};
+use crate::backend::Event;
+use crate::input::{Decoder, ESCAPE_TIMEOUT};
+
+/// Opens `/dev/tty` and puts it in raw mode until the terminal's dropped. This works
+/// even if stdin and stdout have been redirected.
+pub(crate) fn tty() -> io::Result<Terminal> {
+    let tty = termion::get_tty()?;
+    let reader = tty.try_clone()?;
+    let original = termios(&tty)?;
+    let panic_tty = tty.try_clone()?;
+    let restore_tty = tty.try_clone()?;
+    let output = tty.try_clone()?.into_raw_mode()?;
+
+    let (sender, receiver) = mpsc::channel();
+    let stop = Arc::new(AtomicBool::new(false));
+    let reader_stop = Arc::clone(&stop);
+    thread::spawn(move || read_tty(reader, sender, reader_stop));
+
+    let input = Bytes::new(receiver, Some(tty), stop);
+    let mut terminal = Terminal::new(Box::new(output), Box::new(input), true);
+    terminal.clean_up_on_panic(panic_tty, move || {
+        // SAFETY: `original` came from tcgetattr on this terminal.
+        unsafe { libc::tcsetattr(restore_tty.as_raw_fd(), libc::TCSANOW, &original) };
+    });
+    Ok(terminal)
+}
+
+/// Uses `input` and `output` as they are. If they're a terminal it's up to the caller
+/// to put it in raw mode. `input` is read on a thread that carries on until it ends, as
+/// a blocking read can't be interrupted.
+pub(crate) fn with_io(mut input: Box<dyn Read + Send>, output: Box<dyn Write>) -> Terminal {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buf = [0; 1024];
+        loop {
+            match input.read(&mut buf) {
+                Ok(n @ 1..) => {
+                    if buf[..n].iter().any(|&b| sender.send(b).is_err()) {
+                        return;
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                _ => return,
+            }
+        }
+    });
+
+    let input = Bytes::new(receiver, None, Arc::new(AtomicBool::new(false)));
+    Terminal::new(output, Box::new(input), false)
+}
+
+/// Bytes from the terminal, and the events they make up.
+struct Bytes {
+    receiver: Receiver<u8>,
+    /// Bytes we've read but haven't turned into events yet.
+    decoder: Decoder,
+    /// The terminal device, if we opened it ourselves.
+    tty: Option<File>,
+    /// Tells the thread reading input to stop.
+    stop: Arc<AtomicBool>,
+    /// Set when the terminal's been resized, and `resize_hook` to stop listening. We
+    /// only listen if we opened the terminal, as otherwise there might not be one.
+    resized: Arc<AtomicBool>,
+    resize_hook: Option<SigId>,
+}
+
+impl Bytes {
+    fn new(receiver: Receiver<u8>, tty: Option<File>, stop: Arc<AtomicBool>) -> Self {
+        let resized = Arc::new(AtomicBool::new(false));
+        // Without this we just won't notice resizes
+        let resize_hook = tty
+            .as_ref()
+            .and_then(|_| signal_hook::flag::register(SIGWINCH, Arc::clone(&resized)).ok());
+        Bytes {
+            receiver,
+            decoder: Decoder::default(),
+            tty,
+            stop,
+            resized,
+            resize_hook,
+        }
+    }
+}
+
+impl Input for Bytes {
+    fn size(&self) -> io::Result<(u16, u16)> {
+        match &self.tty {
+            Some(tty) => termion::terminal_size_fd(tty),
+            None => termion::terminal_size(),
+        }
+    }
+
+    fn event(&mut self, timeout: Duration) -> io::Result<Option<Event>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if self.resized.swap(false, Ordering::Relaxed) {
+                return Ok(Some(Event::Resize));
+            }
+            let received: Vec<u8> = self.receiver.try_iter().collect();
+            let now = Instant::now();
+            self.decoder.push(&received, now);
+            if let Some(event) = self.decoder.next(now) {
+                return Ok(Some(event));
+            }
+            if now >= deadline {
+                return Ok(None);
+            }
+            // A resize doesn't wake us up, and neither does a lone Escape timing out, so
+            // look again every so often.
+            let wait = deadline.duration_since(now).min(ESCAPE_TIMEOUT);
+            match self.receiver.recv_timeout(wait) {
+                Ok(byte) => self.decoder.push(&[byte], Instant::now()),
+                Err(RecvTimeoutError::Timeout) => {}
+                // The input's finished, so whatever's left is all there'll ever be.
+                Err(RecvTimeoutError::Disconnected) => {
+                    return match self.decoder.next(now + ESCAPE_TIMEOUT) {
+                        Some(event) => Ok(Some(event)),
+                        None => Err(io::ErrorKind::UnexpectedEof.into()),
+                    };
+                }
+            }
+        }
+    }
+
+    fn cursor_pos(&mut self) -> io::Result<(u16, u16)> {
+        // Anything the user types in the meantime is kept for later.
+        let deadline = Instant::now() + CURSOR_POS_TIMEOUT;
+        let mut received = vec![];
+        while !received.ends_with(b"R") {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            match self.receiver.recv_timeout(timeout) {
+                Ok(byte) => received.push(byte),
+                Err(_) => {
+                    self.decoder.push(&received, Instant::now());
+                    return Err(io::Error::other("Cursor position detection timed out."));
+                }
+            }
+        }
+        let (start, position) = parse_cursor_pos(&received)?;
+        self.decoder.push(&received[..start], Instant::now());
+        Ok(position)
+    }
+}
+
+impl Drop for Bytes {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(hook) = self.resize_hook {
+            signal_hook::low_level::unregister(hook);
+        }
+    }
+}
+
+/// The terminal's settings before we put it in raw mode.
+fn termios(tty: &File) -> io::Result<libc::termios> {
+    // SAFETY: termios is plain data, and tcgetattr fills it in.
+    let mut termios = unsafe { std::mem::zeroed() };
+    match unsafe { libc::tcgetattr(tty.as_raw_fd(), &mut termios) } {
+        0 => Ok(termios),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+/// Sends bytes from `tty` until told to stop. We poll rather than block on reading, so
+/// that we stop promptly and don't swallow keys meant for whoever reads the terminal next.
+fn read_tty(mut tty: File, sender: Sender<u8>, stop: Arc<AtomicBool>) {
+    let mut poll_fd = libc::pollfd {
+        fd: tty.as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    let mut buf = [0; 1024];
+    while !stop.load(Ordering::Relaxed) {
+        // SAFETY: `poll_fd` is a valid pollfd for an open file descriptor.
+        match unsafe { libc::poll(&mut poll_fd, 1, 50) } {
+            0 => continue,
+            n if n < 0 => match io::Error::last_os_error().kind() {
+                io::ErrorKind::Interrupted => continue,
+                _ => return,
+            },
+            _ => {}
+        }
+        match tty.read(&mut buf) {
+            Ok(n @ 1..) => {
+                if buf[..n].iter().any(|&b| sender.send(b).is_err()) {
+                    return;
+                }
+            }
+            _ => return,
+        }
+    }
+}
@@ -0,0 +1,499 @@
+//! The finder's colours.
+//!
+//! Pick one of the built-in themes, or describe one the way fzf's `--color` option
+//! does, e.g. `light,prompt:#ff8700,bg+:254`:
+//!
+//! ```
+//! use fuzzy_finder::theme::{Colour, Theme};
+//!
+//! let theme = Theme::parse("light,prompt:#ff8700,bg+:254").unwrap();
+//! assert_eq!(theme.prompt, Colour::Rgb(0xff, 0x87, 0x00));
+//! assert_eq!(theme.selected, Colour::Ansi(254));
+//! ```
+//!
+//! Whatever the theme, the finder only uses the colours the terminal can show. It uses
+//! none at all if the `NO_COLOR` environment variable is set or `TERM` is `dumb`. When
+//! the selected item or the matches end up without colours, they're shown in reverse
+//! video and underlined instead.
+use std::env;
+
+use crate::state::FrameItem;
+use crate::{Error, Result};
+
+/// A colour in a [`Theme`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Colour {
+    /// Whatever the terminal uses when nothing's been set.
+    Default,
+    /// One of the terminal's 256 colours. The first 16 are the ones the terminal's own
+    /// theme picks, so they go with whatever it looks like.
+    Ansi(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl Colour {
+    /// `text` drawn in this colour, with ANSI escape codes.
+    pub fn fg(self, text: &str) -> String {
+        match self {
+            Colour::Default => text.to_string(),
+            Colour::Ansi(n) => format!("\x1B[38;5;{n}m{text}\x1B[39m"),
+            Colour::Rgb(r, g, b) => format!("\x1B[38;2;{r};{g};{b}m{text}\x1B[39m"),
+        }
+    }
+
+    /// `text` drawn on this colour, with ANSI escape codes.
+    pub fn bg(self, text: &str) -> String {
+        match self {
+            Colour::Default => text.to_string(),
+            Colour::Ansi(n) => format!("\x1B[48;5;{n}m{text}\x1B[49m"),
+            Colour::Rgb(r, g, b) => format!("\x1B[48;2;{r};{g};{b}m{text}\x1B[49m"),
+        }
+    }
+
+    /// The nearest colour the terminal can show.
+    fn for_depth(self, depth: ColourDepth) -> Colour {
+        match (self, depth) {
+            (_, ColourDepth::None) => Colour::Default,
+            (Colour::Ansi(n), ColourDepth::Basic) if n < 16 => self,
+            (_, ColourDepth::Basic) => Colour::Default,
+            (Colour::Rgb(r, g, b), ColourDepth::Ansi256) => Colour::Ansi(nearest_ansi(r, g, b)),
+            _ => self,
+        }
+    }
+
+    /// Understands what fzf does: `-1` for the default, a number from the 256 colour
+    /// palette, `#rrggbb`, or the name of one of the 16 basic colours.
+    fn parse(colour: &str) -> Result<Colour> {
+        const NAMES: [&str; 8] = [
+            "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+        ];
+        if colour == "-1" || colour == "default" {
+            return Ok(Colour::Default);
+        }
+        if let Ok(n) = colour.parse::<u8>() {
+            return Ok(Colour::Ansi(n));
+        }
+        if let Some(hex) = colour.strip_prefix('#') {
+            let channel = |i: usize| {
+                hex.get(i..i + 2)
+                    .and_then(|c| u8::from_str_radix(c, 16).ok())
+            };
+            return match (hex.len(), channel(0), channel(2), channel(4)) {
+                (6, Some(r), Some(g), Some(b)) => Ok(Colour::Rgb(r, g, b)),
+                _ => Err(Error::InvalidTheme(format!(
+                    "{colour} isn't a colour, expected #rrggbb"
+                ))),
+            };
+        }
+        let (bright, name) = match colour.strip_prefix("bright-") {
+            Some(name) => (8, name),
+            None => (0, colour),
+        };
+        NAMES
+            .iter()
+            .position(|&n| n == name)
+            .map(|n| Colour::Ansi(n as u8 + bright))
+            .ok_or_else(|| Error::InvalidTheme(format!("{colour} isn't a colour")))
+    }
+}
+
+/// The colours of each part of the finder.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Theme {
+    /// The text in front of the search term. fzf calls this `prompt`.
+    pub prompt: Colour,
+    /// The `>` next to the selected item. fzf calls this `pointer`.
+    pub pointer: Colour,
+    /// The `*` next to marked items. fzf calls this `marker`.
+    pub marker: Colour,
+    /// The count of matches under the items. fzf calls this `info`.
+    pub info: Colour,
+    /// The line between the items and the preview. fzf calls this `border`.
+    pub border: Colour,
+    /// Behind the pointer and marker. fzf calls this `gutter`.
+    pub gutter: Colour,
+    /// Behind the selected item. fzf calls this `bg+`.
+    pub selected: Colour,
+    /// The matched parts of items. fzf calls this `hl`.
+    pub highlight: Colour,
+    /// Behind the matched parts of items. fzf doesn't have this.
+    pub highlight_bg: Colour,
+}
+
+impl Theme {
+    /// For terminals with a dark background. This is the default.
+    pub fn dark() -> Self {
+        Theme {
+            prompt: Colour::Rgb(113, 190, 242),
+            pointer: Colour::Rgb(168, 204, 140),
+            marker: Colour::Rgb(219, 171, 121),
+            info: Colour::Rgb(185, 191, 202),
+            border: Colour::Rgb(185, 191, 202),
+            gutter: Colour::Rgb(50, 50, 50),
+            selected: Colour::Rgb(50, 50, 50),
+            highlight: Colour::Default,
+            highlight_bg: Colour::Rgb(13, 88, 138),
+        }
+    }
+
+    /// For terminals with a light background.
+    pub fn light() -> Self {
+        Theme {
+            prompt: Colour::Rgb(0, 95, 175),
+            pointer: Colour::Rgb(0, 135, 0),
+            marker: Colour::Rgb(175, 95, 0),
+            info: Colour::Rgb(108, 108, 108),
+            border: Colour::Rgb(108, 108, 108),
+            gutter: Colour::Rgb(228, 228, 228),
+            selected: Colour::Rgb(228, 228, 228),
+            highlight: Colour::Default,
+            highlight_bg: Colour::Rgb(175, 215, 255),
+        }
+    }
+
+    /// Leaves everything in the terminal's default colours.
+    pub fn no_colour() -> Self {
+        Theme {
+            prompt: Colour::Default,
+            pointer: Colour::Default,
+            marker: Colour::Default,
+            info: Colour::Default,
+            border: Colour::Default,
+            gutter: Colour::Default,
+            selected: Colour::Default,
+            highlight: Colour::Default,
+            highlight_bg: Colour::Default,
+        }
+    }
+
+    /// Parses a theme written the way fzf's `--color` option takes it: comma separated
+    /// `name:colour` pairs, optionally starting with a base theme of `dark`, `light` or
+    /// `bw`. Parts of fzf's themes that the finder doesn't have, and text attributes
+    /// like `bold`, are ignored, so fzf themes can be used as they are.
+    pub fn parse(spec: &str) -> Result<Theme> {
+        let mut theme = Theme::default();
+        for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (name, value) = match part.split_once(':') {
+                Some(pair) => pair,
+                None => {
+                    theme = match part {
+                        "dark" => Theme::dark(),
+                        "light" => Theme::light(),
+                        "bw" => Theme::no_colour(),
+                        _ => {
+                            return Err(Error::InvalidTheme(format!(
+                                "{part} isn't a theme, expected dark, light or bw"
+                            )))
+                        }
+                    };
+                    continue;
+                }
+            };
+            // The colour can come before or after attributes, e.g. `hl:bold:red`
+            let mut colour = None;
+            for value in value.split(':') {
+                if !ATTRIBUTES.contains(&value) {
+                    colour = Some(Colour::parse(value)?);
+                }
+            }
+            let colour = match colour {
+                Some(colour) => colour,
+                None => continue,
+            };
+            match name {
+                "prompt" => theme.prompt = colour,
+                "pointer" => theme.pointer = colour,
+                "marker" => theme.marker = colour,
+                "info" | "spinner" => theme.info = colour,
+                "border" => theme.border = colour,
+                "gutter" => theme.gutter = colour,
+                "bg+" => theme.selected = colour,
+                "hl" | "hl+" => theme.highlight = colour,
+                _ if IGNORED.contains(&name) => {}
+                _ => {
+                    return Err(Error::InvalidTheme(format!(
+                        "{name} isn't something that can be coloured"
+                    )))
+                }
+            }
+        }
+        Ok(theme)
+    }
+}
+
+impl Theme {
+    /// This theme with only the colours the terminal can show, e.g. with
+    /// [`ColourDepth::detect`]. The finder and its ratatui widget do this themselves.
+    pub fn for_depth(self, depth: ColourDepth) -> Theme {
+        Theme {
+            prompt: self.prompt.for_depth(depth),
+            pointer: self.pointer.for_depth(depth),
+            marker: self.marker.for_depth(depth),
+            info: self.info.for_depth(depth),
+            border: self.border.for_depth(depth),
+            gutter: self.gutter.for_depth(depth),
+            selected: self.selected.for_depth(depth),
+            highlight: self.highlight.for_depth(depth),
+            highlight_bg: self.highlight_bg.for_depth(depth),
+        }
+    }
+
+    /// Draws `text` as part of the selected item, in reverse video if there's no colour
+    /// for it.
+    pub(crate) fn paint_selected(&self, text: &str) -> String {
+        match self.selected {
+            Colour::Default => format!("\x1B[7m{text}\x1B[27m"),
+            colour => colour.bg(text),
+        }
+    }
+
+    /// Draws `text` as a match, underlined if there are no colours for it.
+    pub(crate) fn paint_match(&self, text: &str) -> String {
+        match (self.highlight, self.highlight_bg) {
+            (Colour::Default, Colour::Default) => format!("\x1B[4m{text}\x1B[24m"),
+            (fg, bg) => bg.bg(&fg.fg(text)),
+        }
+    }
+
+    /// Draws an item with what matched highlighted, and the pointer and marker in front,
+    /// with ANSI escape codes. For [`Backend`](crate::backend::Backend)s that draw on a
+    /// terminal of their own.
+    pub fn paint_item(&self, item: &FrameItem) -> String {
+        let mut line = String::new();
+        for (matched, run) in &item.runs {
+            let run = if *matched {
+                self.paint_match(run)
+            } else {
+                run.clone()
+            };
+            if item.selected {
+                line.push_str(&self.paint_selected(&run));
+            } else {
+                line.push_str(&run);
+            }
+        }
+        let marker = if item.marked {
+            format!("{} ", self.marker.fg("*"))
+        } else {
+            String::from("  ")
+        };
+        let pointer = if item.selected {
+            self.gutter.bg(&self.pointer.fg(">"))
+        } else {
+            self.gutter.bg(" ")
+        };
+        format!("{pointer}{marker}{line}")
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::dark()
+    }
+}
+
+/// How many colours the terminal can show.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColourDepth {
+    /// No colours at all, e.g. because `NO_COLOR` is set.
+    None,
+    /// The 8 or 16 colours the terminal's own theme picks.
+    Basic,
+    Ansi256,
+    TrueColour,
+}
+
+impl ColourDepth {
+    /// What the terminal we're running in can show, going by the environment.
+    pub fn detect() -> Self {
+        ColourDepth::from_env(
+            env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()),
+            env::var("TERM").ok().as_deref(),
+            env::var("COLORTERM").ok().as_deref(),
+        )
+    }
+
+    /// There's no reliable way to ask a terminal, so like most programs we go by
+    /// `TERM` and `COLORTERM`. Anything we don't recognise is assumed to be a modern
+    /// terminal with true colour.
+    fn from_env(no_color: bool, term: Option<&str>, colorterm: Option<&str>) -> Self {
+        let term = term.unwrap_or("dumb");
+        if no_color || term == "dumb" {
+            ColourDepth::None
+        } else if matches!(colorterm, Some("truecolor" | "24bit")) {
+            ColourDepth::TrueColour
+        } else if term.contains("256color") {
+            ColourDepth::Ansi256
+        } else if ["linux", "vt100", "vt102", "vt220", "ansi", "cons25"].contains(&term)
+            || term.ends_with("-color")
+            || term.ends_with("-8color")
+            || term.ends_with("-16color")
+        {
+            ColourDepth::Basic
+        } else {
+            ColourDepth::TrueColour
+        }
+    }
+}
+
+/// The closest colour to `(r, g, b)` in the 6x6x6 cube or the grey ramp of the 256
+/// colour palette.
+fn nearest_ansi(r: u8, g: u8, b: u8) -> u8 {
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    let nearest_level = |c: u8| {
+        (0..6)
+            .min_by_key(|&i| (LEVELS[i] as i32 - c as i32).abs())
+            .unwrap()
+    };
+    let (ri, gi, bi) = (nearest_level(r), nearest_level(g), nearest_level(b));
+    let cube = (LEVELS[ri], LEVELS[gi], LEVELS[bi]);
+    let grey_index = ((r as u32 + g as u32 + b as u32) / 3).saturating_sub(3) / 10;
+    let grey_index = grey_index.min(23) as u8;
+    let grey = 8 + grey_index * 10;
+
+    let distance = |(x, y, z): (u8, u8, u8)| {
+        [(x, r), (y, g), (z, b)]
+            .iter()
+            .map(|&(a, b)| (a as i32 - b as i32).pow(2))
+            .sum::<i32>()
+    };
+    if distance((grey, grey, grey)) < distance(cube) {
+        232 + grey_index
+    } else {
+        16 + 36 * ri as u8 + 6 * gi as u8 + bi as u8
+    }
+}
+
+/// Text attributes fzf understands, which we don't do anything with.
+const ATTRIBUTES: &[&str] = &[
+    "regular",
+    "bold",
+    "underline",
+    "reverse",
+    "dim",
+    "italic",
+    "strikethrough",
+    "blink",
+];
+
+/// Parts of fzf's themes that the finder doesn't have.
+const IGNORED: &[&str] = &[
+    "fg",
+    "bg",
+    "fg+",
+    "preview-fg",
+    "preview-bg",
+    "query",
+    "disabled",
+    "separator",
+    "scrollbar",
+    "label",
+    "preview-label",
+    "header",
+    "preview-border",
+    "preview-scrollbar",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        // WHEN
+        let theme = Theme::parse("bw,hl:underline:bright-red,prompt:-1,bg+:#1a2B3c,fg:7").unwrap();
+
+        // THEN
+        assert_eq!(
+            theme,
+            Theme {
+                highlight: Colour::Ansi(9),
+                selected: Colour::Rgb(0x1a, 0x2b, 0x3c),
+                ..Theme::no_colour()
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(
+            Theme::parse("hl:mauve"),
+            Err(Error::InvalidTheme(_))
+        ));
+        assert!(Theme::parse("hl:#12345").is_err());
+        assert!(Theme::parse("sparkle:red").is_err());
+        assert!(Theme::parse("solarized").is_err());
+    }
+
+    #[test]
+    fn test_no_colour_is_plain() {
+        // WHEN
+        let text = Colour::Default.bg(&Colour::Default.fg("Gollum"));
+
+        // THEN
+        assert_eq!(text, "Gollum");
+    }
+
+    #[test]
+    fn test_colour_depth() {
+        assert_eq!(
+            ColourDepth::from_env(true, Some("xterm-256color"), Some("truecolor")),
+            ColourDepth::None
+        );
+        assert_eq!(
+            ColourDepth::from_env(false, Some("dumb"), None),
+            ColourDepth::None
+        );
+        assert_eq!(ColourDepth::from_env(false, None, None), ColourDepth::None);
+        assert_eq!(
+            ColourDepth::from_env(false, Some("linux"), None),
+            ColourDepth::Basic
+        );
+        assert_eq!(
+            ColourDepth::from_env(false, Some("screen-256color"), None),
+            ColourDepth::Ansi256
+        );
+        assert_eq!(
+            ColourDepth::from_env(false, Some("xterm-256color"), Some("truecolor")),
+            ColourDepth::TrueColour
+        );
+    }
+
+    #[test]
+    fn test_for_depth() {
+        // GIVEN
+        let theme = Theme {
+            prompt: Colour::Ansi(4),
+            ..Theme::dark()
+        };
+
+        // WHEN
+        let ansi = theme.for_depth(ColourDepth::Ansi256);
+        let basic = theme.for_depth(ColourDepth::Basic);
+
+        // THEN
+        assert_eq!(ansi.selected, Colour::Ansi(236));
+        assert_eq!(ansi.highlight_bg, Colour::Ansi(24));
+        assert_eq!(
+            basic,
+            Theme {
+                prompt: Colour::Ansi(4),
+                ..Theme::no_colour()
+            }
+        );
+    }
+
+    #[test]
+    fn test_monochrome() {
+        // GIVEN
+        let theme = Theme::dark().for_depth(ColourDepth::None);
+
+        // WHEN
+        let selected = theme.paint_selected("Frodo");
+        let matched = theme.paint_match("F");
+
+        // THEN
+        assert_eq!(selected, "\x1b[7mFrodo\x1b[27m");
+        assert_eq!(matched, "\x1b[4mF\x1b[24m");
+    }
+}
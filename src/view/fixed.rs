@@ -10,6 +10,13 @@ impl FixedView {
         assert!(capacity > 0);
         Self { capacity, index: 0 }
     }
+
+    /// Changes how many items are shown. The selection moves down if it no longer fits.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        assert!(capacity > 0);
+        self.index = self.index.min(capacity - 1);
+        self.capacity = capacity;
+    }
 }
 impl View for FixedView {
     fn render<'a, T>(&mut self, items: &'a [T]) -> Render<&'a T> {
@@ -22,7 +29,9 @@ impl View for FixedView {
             }
             let below = items[0..self.index].iter().collect();
             let selected = &items[self.index];
-            let above = items[self.index + 1..self.capacity].iter().collect();
+            let above = (self.index + 1..self.capacity)
+                .flat_map(|i| items.get(i))
+                .collect();
             Render::NonEmpty {
                 above,
                 selected,
@@ -40,4 +49,35 @@ impl View for FixedView {
     fn down(&mut self) {
         self.index = self.index.saturating_sub(1);
     }
+
+    fn page_up(&mut self) {
+        self.index = self.capacity - 1;
+    }
+
+    fn page_down(&mut self) {
+        self.index = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FEW_ITEMS: &[&str] = &["A", "B", "C"];
+
+    #[test]
+    fn test_fewer_items_than_capacity() {
+        // GIVEN
+        let mut view = FixedView::new(8);
+
+        // WHEN
+        let all = view.render(FEW_ITEMS).len();
+        view.page_up();
+        let top = view.render(FEW_ITEMS).selected().copied();
+
+        // THEN
+        assert_eq!(all, 3);
+        assert_eq!(top, Some(&"C"));
+        assert_eq!(view.index, 2);
+    }
 }
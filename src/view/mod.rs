@@ -6,12 +6,92 @@ mod scrolling;
 pub use fixed::FixedView;
 pub use scrolling::ScrollingView;
 
+/// Which kind of view the finder uses to lay out its matches.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ViewStrategy {
+    /// See [`ScrollingView`].
+    #[default]
+    Scrolling,
+    /// See [`FixedView`].
+    Fixed,
+}
+
 pub trait View {
     fn up(&mut self);
     fn down(&mut self);
+    /// Moves the selection a page towards the worse matches.
+    fn page_up(&mut self);
+    /// Moves the selection a page towards the best match.
+    fn page_down(&mut self);
     fn render<'a, T>(&mut self, items: &'a [T]) -> Render<&'a T>;
 }
 
+/// A view chosen at runtime from a [`ViewStrategy`].
+pub(crate) enum AnyView {
+    Scrolling(ScrollingView),
+    Fixed(FixedView),
+}
+
+impl AnyView {
+    pub fn new(strategy: ViewStrategy, capacity: usize) -> Self {
+        match strategy {
+            ViewStrategy::Scrolling => AnyView::Scrolling(ScrollingView::new(capacity)),
+            ViewStrategy::Fixed => AnyView::Fixed(FixedView::new(capacity)),
+        }
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        match self {
+            AnyView::Scrolling(view) => view.set_capacity(capacity),
+            AnyView::Fixed(view) => view.set_capacity(capacity),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        match self {
+            AnyView::Scrolling(view) => view.capacity,
+            AnyView::Fixed(view) => view.capacity,
+        }
+    }
+}
+
+impl View for AnyView {
+    fn up(&mut self) {
+        match self {
+            AnyView::Scrolling(view) => view.up(),
+            AnyView::Fixed(view) => view.up(),
+        }
+    }
+
+    fn down(&mut self) {
+        match self {
+            AnyView::Scrolling(view) => view.down(),
+            AnyView::Fixed(view) => view.down(),
+        }
+    }
+
+    fn page_up(&mut self) {
+        match self {
+            AnyView::Scrolling(view) => view.page_up(),
+            AnyView::Fixed(view) => view.page_up(),
+        }
+    }
+
+    fn page_down(&mut self) {
+        match self {
+            AnyView::Scrolling(view) => view.page_down(),
+            AnyView::Fixed(view) => view.page_down(),
+        }
+    }
+
+    fn render<'a, T>(&mut self, items: &'a [T]) -> Render<&'a T> {
+        match self {
+            AnyView::Scrolling(view) => view.render(items),
+            AnyView::Fixed(view) => view.render(items),
+        }
+    }
+}
+
 pub enum Render<T> {
     Empty,
     NonEmpty {
@@ -41,6 +121,10 @@ impl<T> Render<T> {
         }
     }
 
+    pub fn is_empty(&self) -> bool {
+        matches!(self, Render::Empty)
+    }
+
     pub fn len(&self) -> usize {
         match self {
             Render::Empty => 0,
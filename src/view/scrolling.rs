@@ -15,6 +15,16 @@ impl ScrollingView {
             skip: 0,
         }
     }
+
+    /// Changes how many items are shown, keeping the same item selected.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        assert!(capacity > 0);
+        if self.index >= capacity {
+            self.skip += self.index + 1 - capacity;
+            self.index = capacity - 1;
+        }
+        self.capacity = capacity;
+    }
 }
 
 impl View for ScrollingView {
@@ -59,9 +69,23 @@ impl View for ScrollingView {
             self.skip = self.skip.saturating_sub(1);
         }
     }
+
+    fn page_up(&mut self) {
+        // Rendering pulls the selection back if this goes past the last item
+        for _ in 0..self.capacity {
+            self.up();
+        }
+    }
+
+    fn page_down(&mut self) {
+        for _ in 0..self.capacity {
+            self.down();
+        }
+    }
 }
 
 #[cfg(test)]
+#[allow(clippy::needless_borrow)]
 mod tests {
     use super::*;
 
@@ -176,4 +200,44 @@ mod tests {
         assert_eq!(result.len(), 3);
         assert_eq!(result.num_above(), 0);
     }
+
+    #[test]
+    fn test_page_up_and_down() {
+        // GIVEN
+        let mut view = ScrollingView::new(4);
+
+        // WHEN
+        view.up();
+        view.page_up();
+        let up = view.render(ITEMS).selected().copied();
+        view.page_up();
+        view.page_up();
+        let end = view.render(ITEMS).selected().copied();
+        view.page_down();
+        let down = view.render(ITEMS).selected().copied();
+
+        // THEN
+        assert_eq!(up, Some(&"F"));
+        assert_eq!(end, Some(&"M"));
+        assert_eq!(down, Some(&"I"));
+    }
+
+    #[test]
+    fn test_set_capacity() {
+        // GIVEN
+        let mut view = ScrollingView::new(8);
+        for _ in 0..6 {
+            view.up();
+        }
+
+        // WHEN
+        view.set_capacity(4);
+        let smaller = view.render(ITEMS).selected().copied();
+        view.set_capacity(10);
+        let bigger = view.render(ITEMS).selected().copied();
+
+        // THEN
+        assert_eq!(smaller, Some(&"G"));
+        assert_eq!(bigger, Some(&"G"));
+    }
 }
@@ -0,0 +1,266 @@
+//! The finder as a [ratatui](https://ratatui.rs) widget, for when it's one part of a
+//! bigger TUI, e.g. a command palette in one pane.
+//!
+//! The application owns the terminal and reads the keys. It keeps a [`FinderState`],
+//! passes keys on to it, and draws it with [`FuzzyFinderWidget`] wherever it likes:
+//!
+//! ```
+//! use fuzzy_finder::item::Item;
+//! use fuzzy_finder::keymap::Key;
+//! use fuzzy_finder::{FindOutcome, FuzzyFinderBuilder, FuzzyFinderWidget};
+//! use ratatui::buffer::Buffer;
+//! use ratatui::layout::Rect;
+//! use ratatui::widgets::StatefulWidget;
+//!
+//! let items = vec![
+//!     Item::new(String::from("Frodo"), 1),
+//!     Item::new(String::from("Sam"), 2),
+//! ];
+//! let mut state = FuzzyFinderBuilder::new().state(items);
+//! state.handle_key(Key::Char('s'));
+//!
+//! let area = Rect::new(0, 0, 20, 3);
+//! let mut buffer = Buffer::empty(area);
+//! FuzzyFinderWidget::new().render(area, &mut buffer, &mut state);
+//! assert_eq!(FuzzyFinderWidget::cursor_position(area, &state).x, 3);
+//!
+//! let outcome = state.handle_key(Key::Char('\n'));
+//! assert!(matches!(outcome, Some(FindOutcome::Selected { item: 2, .. })));
+//! ```
+//!
+//! Preview commands run in the background, so check
+//! [`FinderState::receive_preview`] every so often and redraw when it says one has
+//! finished.
+//!
+//! With the `crossterm` feature too,
+//! [`Key::from_crossterm`](crate::keymap::Key::from_crossterm) turns the key events
+//! ratatui applications usually read into [`Key`](crate::keymap::Key)s.
+use std::marker::PhantomData;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Position, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::StatefulWidget;
+
+use crate::state::{FinderState, FrameItem, SPINNER};
+use crate::theme::{Colour, ColourDepth, Theme};
+
+/// Draws a [`FinderState`] the same way the finder draws itself on the terminal: the
+/// matches with the best at the bottom, the number of matches, then the prompt on the
+/// last row. The state is resized to fit the area it's drawn in.
+pub struct FuzzyFinderWidget<T> {
+    theme: Theme,
+    items: PhantomData<fn(T)>,
+}
+
+impl<T> FuzzyFinderWidget<T> {
+    pub fn new() -> Self {
+        FuzzyFinderWidget {
+            theme: Theme::default().for_depth(ColourDepth::detect()),
+            items: PhantomData,
+        }
+    }
+
+    /// The colours to use. See [`crate::theme`]. Like the finder, we only use the ones
+    /// the terminal can show, and none if `NO_COLOR` is set.
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = theme.for_depth(ColourDepth::detect());
+        self
+    }
+
+    /// Where the cursor goes when the finder's drawn in `area`, for
+    /// [`ratatui::Frame::set_cursor_position`].
+    pub fn cursor_position(area: Rect, state: &FinderState<T>) -> Position
+    where
+        T: Clone,
+    {
+        let column = (state.cursor_column() as u16).min(area.width.saturating_sub(1));
+        Position::new(area.x + column, area.bottom().saturating_sub(1))
+    }
+
+    /// An item with what matched highlighted, and the pointer and marker in front.
+    fn item_line(&self, item: &FrameItem) -> Line<'static> {
+        let theme = &self.theme;
+        let gutter = bg(theme.gutter);
+        let pointer = match item.selected {
+            true => Span::styled(">", gutter.patch(fg(theme.pointer))),
+            false => Span::styled(" ", gutter),
+        };
+        let marker = match item.marked {
+            true => Span::styled("* ", fg(theme.marker)),
+            false => Span::raw("  "),
+        };
+        let mut spans = vec![pointer, marker];
+        for (matched, run) in &item.runs {
+            let mut style = Style::default();
+            if item.selected {
+                style = match theme.selected {
+                    Colour::Default => style.add_modifier(Modifier::REVERSED),
+                    colour => style.patch(bg(colour)),
+                };
+            }
+            let uncoloured =
+                theme.highlight == Colour::Default && theme.highlight_bg == Colour::Default;
+            if *matched && uncoloured {
+                style = style.add_modifier(Modifier::UNDERLINED);
+            } else if *matched {
+                style = style
+                    .patch(bg(theme.highlight_bg))
+                    .patch(fg(theme.highlight));
+            }
+            spans.push(Span::styled(run.clone(), style));
+        }
+        Line::from(spans)
+    }
+}
+
+impl<T> Default for FuzzyFinderWidget<T> {
+    fn default() -> Self {
+        FuzzyFinderWidget::new()
+    }
+}
+
+impl<T> StatefulWidget for FuzzyFinderWidget<T>
+where
+    T: Clone,
+{
+    type State = FinderState<T>;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut FinderState<T>) {
+        if area.is_empty() {
+            return;
+        }
+        // Leave room for the info line and the prompt
+        state.resize(
+            area.width as usize,
+            (area.height as usize).saturating_sub(2),
+        );
+        let frame = state.frame();
+
+        let mut lines = vec![Line::default(); frame.height.saturating_sub(frame.items.len())];
+        lines.extend(frame.items.iter().map(|item| self.item_line(item)));
+        let frame_number = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |now| now.as_millis() / 100);
+        let spinner = SPINNER[frame_number as usize % SPINNER.len()];
+        lines.push(Line::styled(frame.info(spinner), fg(self.theme.info)));
+        lines.push(Line::from(vec![
+            Span::styled(frame.prompt.clone(), fg(self.theme.prompt)),
+            Span::raw(" "),
+            Span::raw(frame.query.clone()),
+        ]));
+
+        // If there isn't room for even one item, the prompt matters most.
+        let skip = lines.len().saturating_sub(area.height as usize);
+        for (row, line) in lines.iter().enumerate().skip(skip) {
+            let y = area.y + (row - skip) as u16;
+            buf.set_line(area.x, y, line, area.width);
+            if let (Some(preview), true) = (&frame.preview, row < frame.height) {
+                let x = area.x + frame.list_width as u16;
+                let width = area.right().saturating_sub(x);
+                let text = preview.get(row).map_or("", |line| line.as_str());
+                let border = Span::styled("│", fg(self.theme.border));
+                buf.set_line(x, y, &Line::from(vec![border, Span::raw(text)]), width);
+            }
+        }
+    }
+}
+
+fn colour(colour: Colour) -> Option<Color> {
+    match colour {
+        Colour::Default => None,
+        Colour::Ansi(n) => Some(Color::Indexed(n)),
+        Colour::Rgb(r, g, b) => Some(Color::Rgb(r, g, b)),
+    }
+}
+
+/// Text in `colour`, or as it is for the default.
+fn fg(colour: Colour) -> Style {
+    Style {
+        fg: self::colour(colour),
+        ..Style::default()
+    }
+}
+
+/// Text on `colour`, or as it is for the default.
+fn bg(colour: Colour) -> Style {
+    Style {
+        bg: self::colour(colour),
+        ..Style::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::Item;
+    use crate::keymap::Key;
+    use crate::FuzzyFinderBuilder;
+
+    fn render(state: &mut FinderState<usize>, width: u16, height: u16) -> Buffer {
+        let area = Rect::new(0, 0, width, height);
+        let mut buffer = Buffer::empty(area);
+        FuzzyFinderWidget::new()
+            .theme(Theme::no_colour())
+            .render(area, &mut buffer, state);
+        buffer
+    }
+
+    fn text(buffer: &Buffer) -> Vec<String> {
+        let area = buffer.area;
+        (area.top()..area.bottom())
+            .map(|y| {
+                (area.left()..area.right())
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    fn hobbits() -> FinderState<usize> {
+        let items = ["Frodo", "Sam", "Merry", "Pippin"]
+            .iter()
+            .enumerate()
+            .map(|(i, name)| Item::new(name.to_string(), i))
+            .collect();
+        FuzzyFinderBuilder::new().state(items)
+    }
+
+    #[test]
+    fn test_renders_like_the_terminal() {
+        // GIVEN
+        let mut state = hobbits();
+        state.handle_key(Key::Char('r'));
+
+        // WHEN
+        let buffer = render(&mut state, 20, 5);
+
+        // THEN
+        let info = state.frame().info(' ');
+        assert_eq!(
+            text(&buffer),
+            ["", "   Merry", ">  Frodo", info.trim_end(), "$ r"]
+        );
+        assert!(buffer[(3, 2)].modifier.contains(Modifier::REVERSED));
+        assert_eq!(
+            FuzzyFinderWidget::cursor_position(buffer.area, &state),
+            Position::new(3, 4)
+        );
+    }
+
+    #[test]
+    fn test_keeps_the_prompt_when_cramped() {
+        // GIVEN
+        let mut state = hobbits();
+
+        // WHEN
+        let buffer = render(&mut state, 20, 2);
+
+        // THEN
+        assert_eq!(text(&buffer)[1], "$");
+    }
+}